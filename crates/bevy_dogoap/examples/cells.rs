@@ -70,7 +70,7 @@ fn spawn_cell(commands: &mut Commands, position: Vec3, speed: f32) {
         .set_cost(1);
 
     let replicate_action = ReplicateAction::action()
-        .with_precondition(Hunger::is_less(10.0))
+        .with_precondition(Hunger::is_at_most(10.0))
         .with_mutator(IsReplicating::set(true))
         .with_mutator(Hunger::increase(25.0))
         .set_cost(10);
@@ -118,28 +118,28 @@ fn startup(mut commands: Commands, window: Single<&Window>) {
         let gold_goal = Goal::from_reqs(&[GoldAmount::is(3)]);
 
         let sleep_action = SleepAction::action()
-            .with_precondition(Energy::is_less(50.0))
+            .with_precondition(Energy::is_at_most(50.0))
             .with_precondition(AtLocation::is(Location::House))
             .with_mutator(Energy::increase(100.0))
             .set_cost(1);
 
         let eat_action = EatAction::action()
-            .with_precondition(Hunger::is_more(50.0))
+            .with_precondition(Hunger::is_at_least(50.0))
             .with_precondition(AtLocation::is(Location::Mushroom))
             .with_mutator(Hunger::decrease(25.0))
             .with_mutator(AtLocation::set(Location::Outside))
             .set_cost(2);
 
         let mine_ore_action = MineOreAction::action()
-            .with_precondition(Energy::is_more(10.0))
-            .with_precondition(Hunger::is_less(75.0))
+            .with_precondition(Energy::is_at_least(10.0))
+            .with_precondition(Hunger::is_at_most(75.0))
             .with_precondition(AtLocation::is(Location::Ore))
             .with_mutator(HasOre::set(true))
             .set_cost(3);
 
         let smelt_ore_action = SmeltOreAction::action()
-            .with_precondition(Energy::is_more(10.0))
-            .with_precondition(Hunger::is_less(75.0))
+            .with_precondition(Energy::is_at_least(10.0))
+            .with_precondition(Hunger::is_at_most(75.0))
             .with_precondition(AtLocation::is(Location::Smelter))
             .with_precondition(HasOre::is(true))
             .with_mutator(HasOre::set(false))
@@ -147,8 +147,8 @@ fn startup(mut commands: Commands, window: Single<&Window>) {
             .set_cost(4);
 
         let sell_metal_action = SellMetalAction::action()
-            .with_precondition(Energy::is_more(10.0))
-            .with_precondition(Hunger::is_less(75.0))
+            .with_precondition(Energy::is_at_least(10.0))
+            .with_precondition(Hunger::is_at_most(75.0))
             .with_precondition(AtLocation::is(Location::Merchant))
             .with_precondition(HasMetal::is(true))
             .with_mutator(GoldAmount::increase(1))
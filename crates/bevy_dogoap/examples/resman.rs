@@ -509,7 +509,7 @@ fn setup(mut commands: Commands) {
 }
 
 fn spawn_customer(commands: &mut Commands, name: &str, thirst_initial: f64, pos: Vec3) {
-    let customer_goal = Goal::from_reqs(&[Thirst::is_less(THIRST_THRESHOLD)]);
+    let customer_goal = Goal::from_reqs(&[Thirst::is_at_most(THIRST_THRESHOLD)]);
 
     let drink = DrinkLemonade::action()
         .with_precondition(CarryingItem::is(Item::Lemonade))
@@ -783,7 +783,7 @@ fn update_customer_goal(mut commands: Commands, mut q: Query<(Entity, &mut Plann
     for (e, mut planner, thirst) in q.iter_mut() {
         if thirst.0 > THIRST_THRESHOLD {
             if planner.goals.is_empty() {
-                let goal = Goal::from_reqs(&[Thirst::is_less(THIRST_THRESHOLD)]);
+                let goal = Goal::from_reqs(&[Thirst::is_at_most(THIRST_THRESHOLD)]);
                 set_goals_and_replan(&mut commands, e, &mut planner, vec![goal]);
             }
         } else {
@@ -809,7 +809,7 @@ fn update_worker_goal(
     for (e, mut planner, should_go, pending, energy) in q.iter_mut() {
         // Rest goal has priority when energy is low
         if energy.0 < ENERGY_LOW_THRESH {
-            let rest_goal = Goal::from_reqs(&[Energy::is_more(ENERGY_TARGET)]);
+            let rest_goal = Goal::from_reqs(&[Energy::is_at_least(ENERGY_TARGET)]);
             set_goals_and_replan(&mut commands, e, &mut planner, vec![rest_goal]);
             continue;
         }
@@ -4,11 +4,19 @@ use crate::prelude::*;
 use bevy_ecs::entity_disabling::Disabled;
 use bevy_platform::collections::HashMap;
 use core::fmt;
+use std::cell::RefCell;
+use std::sync::{Arc, Mutex, PoisonError};
 
 #[cfg(feature = "compute-pool")]
 use {bevy_tasks::AsyncComputeTaskPool, crossbeam_channel::Receiver};
 
-use dogoap::prelude::*;
+/// A formulated plan. This is created and inserted into [`Planner`] for you when you trigger [`UpdatePlan`].
+pub use dogoap::planner::Plan;
+
+use dogoap::{
+    planner::{CompiledDomain, IncrementalPlanner, PlanningContext},
+    prelude::*,
+};
 
 // TODO can we replace this with ActionComponent perhaps? Should be able to
 type ActionsMap = HashMap<String, (Action, Box<dyn InserterComponent>)>;
@@ -27,14 +35,44 @@ pub struct Planner {
     pub goals: Vec<Goal>,
     /// What [`Action`] we're currrently trying to execute
     pub current_action: Option<Action>,
-    /// The currently executed plan
+    /// The currently executed plan, left as it was found while its steps are executed
     pub current_plan: Option<Plan>,
+    /// Index of the step of [`Self::current_plan`] that's being executed, or the next one to execute
+    /// if none is. Only moves on once the action of that step is done. Set back to `0` whenever a
+    /// new plan replaces it, so do the same when setting [`Self::current_plan`] yourself.
+    pub current_step: usize,
+    /// Limits and settings used every time this [`Planner`] makes a new plan
+    pub plan_options: PlanOptions,
+    /// When planning runs into one of the limits in [`Self::plan_options`], use the plan that gets
+    /// closest to the goal instead of giving up, so we keep making progress. The [`Plan`] is marked
+    /// with [`Plan::partial`], and you'll have to trigger [`UpdatePlan`] again once it's done.
+    pub anytime_planning: bool,
+    /// Keep the search for each goal around between plans, and only repair the parts of it
+    /// that changed instead of starting over, see [`IncrementalPlanner`]. Always searches from the
+    /// goal back to the start, and takes precedence over [`Self::anytime_planning`].
+    pub incremental_planning: bool,
+    /// Before planning from scratch, check whether the rest of [`Self::current_plan`] still
+    /// reaches its goal from the current state, and only plan the steps needed to get back to it
+    /// if it doesn't, see [`repair_plan`](dogoap::planner::repair_plan). Goals with a higher
+    /// priority than the one of the current plan are only looked at once it can't be repaired.
+    pub repair_plans: bool,
+    /// Seeds [`PlanOptions::seed`] for this planner, taking precedence over the one in
+    /// [`Self::plan_options`]. Give every planner its own seed to have planners with the same
+    /// [`Action`]s and [`Goal`]s pick different plans of the same cost.
+    pub seed: Option<u64>,
 
     // TODO figure out how to get reflect to work, if possible
     #[reflect(ignore)]
     actions_map: ActionsMap,
     /// Internal prepared vector of just [`Action`]
     actions_for_dogoap: Vec<Action>,
+    /// The [`Action`]s compiled for each goal of the last plan, so replanning for the same goals
+    /// doesn't have to compile them again
+    #[reflect(ignore)]
+    domains: Vec<Arc<CompiledDomain>>,
+    /// The searches for each of [`Self::domains`], when using [`Self::incremental_planning`]
+    #[reflect(ignore)]
+    incremental_planners: Vec<(Arc<CompiledDomain>, Arc<Mutex<IncrementalPlanner>>)>,
 }
 
 impl fmt::Debug for Planner {
@@ -49,13 +87,13 @@ impl fmt::Debug for Planner {
 
 /// When we're not using `AsyncComputeTaskPool` + `Task`, we define our own so we can replace less code later
 #[cfg(not(feature = "compute-pool"))]
-struct Receiver<T>(T);
+struct Receiver<T>(Option<T>);
 
 /// This Component holds to-be-processed data for `make_plan`
 /// We do it in a asyncronous manner as `make_plan` blocks and if it takes 100ms, we'll delay frames
 /// by 100ms...
 #[derive(Component)]
-pub(crate) struct PlanReceiver(Receiver<Option<Plan>>);
+pub(crate) struct PlanReceiver(Receiver<Result<Plan, PlanError>>);
 
 /// This Component gets added when the planner for an Entity is currently planning,
 /// and removed once a plan has been created. Normally this will take under 1ms,
@@ -72,6 +110,9 @@ impl Planner {
         for (action, _component) in actions_map.values() {
             actions_for_dogoap.push(action.clone());
         }
+        // The order of the `HashMap` changes between runs, and which of two plans that cost the
+        // same wins depends on the order of the actions
+        actions_for_dogoap.sort_by(|a, b| a.key.cmp(&b.key));
 
         let mut state = LocalState::new();
 
@@ -87,9 +128,72 @@ impl Planner {
             actions_map,
             current_action: None,
             current_plan: None,
+            current_step: 0,
+            plan_options: PlanOptions::default(),
+            anytime_planning: false,
+            incremental_planning: false,
+            repair_plans: false,
+            seed: None,
             actions_for_dogoap,
+            domains: Vec::new(),
+            incremental_planners: Vec::new(),
         }
     }
+
+    /// Returns a [`CompiledDomain`] for each of `goals`, reusing the ones from the last plan
+    fn compiled_domains(&mut self, goals: &[Goal]) -> Vec<Arc<CompiledDomain>> {
+        let domains: Vec<_> = goals
+            .iter()
+            .map(|goal| {
+                self.domains
+                    .iter()
+                    .find(|domain| domain.goal() == goal)
+                    .cloned()
+                    .unwrap_or_else(|| {
+                        Arc::new(CompiledDomain::new(&self.actions_for_dogoap, goal))
+                    })
+            })
+            .collect();
+        self.domains.clone_from(&domains);
+        domains
+    }
+
+    /// Returns an [`IncrementalPlanner`] for each of `domains`, reusing the ones from the last plan
+    /// that were made for the same domain. The others are dropped along with their search.
+    fn incremental_planners(
+        &mut self,
+        domains: &[Arc<CompiledDomain>],
+    ) -> Vec<Arc<Mutex<IncrementalPlanner>>> {
+        let planners: Vec<_> = domains
+            .iter()
+            .map(|domain| {
+                self.incremental_planners
+                    .iter()
+                    .find(|(planned, _)| Arc::ptr_eq(planned, domain))
+                    .cloned()
+                    .unwrap_or_else(|| {
+                        let planner = IncrementalPlanner::new(CompiledDomain::clone(domain));
+                        (domain.clone(), Arc::new(Mutex::new(planner)))
+                    })
+            })
+            .collect();
+        self.incremental_planners.clone_from(&planners);
+        planners.into_iter().map(|(_, planner)| planner).collect()
+    }
+
+    /// Called once the [`ActionComponent`] of [`Self::current_action`] is gone, meaning it's done.
+    /// Moves [`Self::current_step`] past it if it was the step being executed, and returns it.
+    fn finish_current_action(&mut self) -> Option<Action> {
+        let finished = self.current_action.take()?;
+        let current = self
+            .current_plan
+            .as_ref()
+            .and_then(|plan| plan.steps.get(self.current_step));
+        if current.is_some_and(|step| step.action == finished.key) {
+            self.current_step += 1;
+        }
+        Some(finished)
+    }
 }
 
 /// This system "syncs" our [`DatumComponent`]s with the `LocalState` in the [`Planner`]
@@ -109,17 +213,6 @@ pub(crate) fn update_planner_local_state(
     Ok(())
 }
 
-/// A formulated plan. This is created and inserted into [`Planner`] for you when you trigger [`UpdatePlan`].
-#[derive(Debug, Clone, Reflect, PartialEq)]
-pub struct Plan {
-    /// Queue of action keys, last is current
-    pub effects: Vec<Effect>,
-    /// Total cost of the plan. Includes the cost of [`Self::effects`] that were already handled and thus removed.
-    pub cost: usize,
-    /// The goal that is to be achieved by the plan.
-    pub goal: Goal,
-}
-
 /// Entity event that can be triggered on an entity that holds a [`Planner`]
 /// to kickstart a new plan. If a planner is already computing a plan, the event is ignored.
 #[derive(EntityEvent, Clone, Debug)]
@@ -141,12 +234,18 @@ impl From<Entity> for UpdatePlan {
     }
 }
 
+thread_local! {
+    /// Every thread that plans, like each compute-pool worker, reuses its own memory for it
+    static PLANNING_CONTEXT: RefCell<PlanningContext> = RefCell::default();
+}
+
 /// This observer is responsible for finding [`Planner`]s that aren't alreay computing a new plan,
 /// and creates a new task for generating a new plan
 pub(crate) fn create_planner_tasks(
     plan: On<UpdatePlan>,
     mut commands: Commands,
-    planner: Query<&Planner, Without<PlanReceiver>>,
+    mut planner: Query<&mut Planner, Without<PlanReceiver>>,
+    planners_with_actions: Query<&dyn ActionComponent>,
     names: Query<NameOrEntity, Allow<Disabled>>,
 ) {
     let entity = plan.planner;
@@ -160,34 +259,84 @@ pub(crate) fn create_planner_tasks(
             }
         })
         .unwrap_or_else(|_| format!("{entity:?}"));
-    let Ok(planner) = planner.get(entity) else {
+    let Ok(mut planner) = planner.get_mut(entity) else {
         debug!(
             "Started planner on an entity {name} that either is not a planner, is already computing a plan, or has been filtered out by a default filter. Ignoring."
         );
         return;
     };
+    if !planners_with_actions.contains(entity) {
+        // Repair from after the last action if it's done, but keep it in the plan while it runs
+        planner.finish_current_action();
+    }
 
     let state = planner.state.clone();
-    let actions = planner.actions_for_dogoap.clone();
     let goals = plan.goals.clone().unwrap_or_else(|| planner.goals.clone());
+    let domains = planner.compiled_domains(&goals);
+    let incremental_planners = if planner.incremental_planning {
+        planner.incremental_planners(&domains)
+    } else {
+        planner.incremental_planners.clear();
+        Vec::new()
+    };
+    let repairable = planner
+        .current_plan
+        .as_ref()
+        .filter(|plan| planner.repair_plans && !plan.is_partial())
+        .and_then(|plan| {
+            let domain = domains.iter().find(|domain| domain.goal() == &plan.goal)?;
+            let remaining = plan.steps.get(planner.current_step..).unwrap_or_default();
+            Some((domain.clone(), remaining.to_vec()))
+        });
+    let mut options = planner.plan_options.clone();
+    if let Some(seed) = planner.seed {
+        options.seed = Some(seed);
+    }
+    let anytime = planner.anytime_planning;
     let find_plan = move || {
-        goals.into_iter().find_map(|goal| {
+        if let Some((domain, remaining)) = &repairable {
+            let repaired = PLANNING_CONTEXT.with_borrow_mut(|context| {
+                domain.repair_plan_with_context(&state, remaining, &options, context)
+            });
+            match repaired {
+                Ok(plan) => return Ok(plan),
+                Err(err) => {
+                    debug!("Couldn't repair the current plan, planning from scratch: {err}");
+                }
+            }
+        }
+        let mut error = None;
+        let mut partial_plan = None;
+        for (index, domain) in domains.iter().enumerate() {
             // This is the expensive part.
-            let (nodes, cost) = make_plan(&state, &actions[..], &goal)?;
-            if nodes.is_empty() {
-                // This goal has realy been achieved
-                None
+            let plan = if let Some(incremental) = incremental_planners.get(index) {
+                incremental
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .make_plan(&state, &options)
             } else {
-                let mut effects: Vec<_> = get_effects_from_plan(nodes).collect();
-                // Ensure the current effect is last, so we can simply `.pop()` it
-                effects.reverse();
-                Some(Plan {
-                    effects,
-                    cost,
-                    goal,
+                PLANNING_CONTEXT.with_borrow_mut(|context| {
+                    if anytime {
+                        domain.make_plan_anytime_with_context(&state, &options, context)
+                    } else {
+                        domain.make_plan_with_context(&state, &options, context)
+                    }
                 })
+            };
+            match plan {
+                Ok(plan) => {
+                    if !plan.is_partial() {
+                        return Ok(plan);
+                    }
+                    // A complete plan for a goal with lower priority beats a partial one
+                    partial_plan.get_or_insert(plan);
+                }
+                // A goal that's already achieved shouldn't hide why the other goals failed
+                Err(PlanError::GoalAlreadySatisfied) if error.is_some() => {}
+                Err(err) => error = Some(err),
             }
-        })
+        }
+        partial_plan.ok_or(error.unwrap_or(PlanError::SearchExhausted))
     };
 
     #[cfg(feature = "compute-pool")]
@@ -202,7 +351,7 @@ pub(crate) fn create_planner_tasks(
         receiver
     };
     #[cfg(not(feature = "compute-pool"))]
-    let receiver = Receiver(find_plan());
+    let receiver = Receiver(Some(find_plan()));
 
     commands
         .entity(entity)
@@ -226,7 +375,9 @@ pub(crate) fn handle_planner_tasks(
     )]
     for (entity, mut task, mut planner) in query.iter_mut() {
         #[cfg(not(feature = "compute-pool"))]
-        let plan = task.0.0.take();
+        let Some(plan) = task.0.0.take() else {
+            continue;
+        };
 
         #[cfg(feature = "compute-pool")]
         let plan = match task.0.try_recv() {
@@ -241,10 +392,21 @@ pub(crate) fn handle_planner_tasks(
 
         commands.entity(entity).try_remove::<PlanReceiver>();
         match plan {
-            Some(plan) => {
+            Ok(plan) => {
+                if let Some(limit) = plan.partial {
+                    debug!(
+                        "Using a partial plan for entity {entity:?}, as planning stopped: {limit}"
+                    );
+                }
                 planner.current_plan.replace(plan);
+                planner.current_step = 0;
             }
-            None => {
+            Err(PlanError::GoalAlreadySatisfied) => {
+                debug!("All goals are already satisfied for entity {entity:?}");
+                planner.current_action = None;
+                planner.current_plan = None;
+            }
+            Err(err) => {
                 let name = names
                     .get(entity)
                     .map(|n| {
@@ -255,7 +417,7 @@ pub(crate) fn handle_planner_tasks(
                         }
                     })
                     .unwrap_or_else(|_| format!("{entity:?}"));
-                warn!("Failed to make a plan for any goal for entity {name}!");
+                warn!("Failed to make a plan for any goal for entity {name}: {err}");
                 planner.current_action = None;
                 planner.current_plan = None;
             }
@@ -275,23 +437,22 @@ pub(crate) fn execute_plan(
             // Already executing an action
             continue;
         }
-        let Some(plan) = planner.current_plan.as_mut() else {
+        let finished = planner.finish_current_action();
+        let Some(plan) = planner.current_plan.as_ref() else {
             debug!("No plan to execute");
-            planner.current_action = None;
             continue;
         };
-        match plan.effects.pop() {
+        let next = plan.steps.get(planner.current_step);
+        match next.map(|step| step.action.clone()) {
             Some(action_name) => {
                 let (found_action, action_component) =
-                planner.actions_map.get(&action_name.action).unwrap_or_else(|| {
+                planner.actions_map.get(&action_name).unwrap_or_else(|| {
                     panic!(
                         "Didn't find action {action_name:?} registered in the Planner::actions_map"
                     )
                 });
 
-                if planner.current_action.is_some()
-                    && Some(found_action) != planner.current_action.as_ref()
-                {
+                if finished.is_some() && Some(found_action) != finished.as_ref() {
                     // We used to work towards a different action, so lets remove that one first.
                     // action_component.remove(&mut commands, entity);
                     // WARN remove all possible actions in order to avoid race conditions for now
@@ -306,7 +467,6 @@ pub(crate) fn execute_plan(
             None => {
                 debug!("Current plan is finished");
                 planner.current_plan = None;
-                planner.current_action = None;
             }
         }
     }
@@ -3,7 +3,12 @@
 // TODO change to upstream once available
 pub use bevy_trait_query::RegisterExt;
 
-pub use dogoap::prelude::{Action, Compare, Datum, Goal, LocalState, Mutator};
+pub use dogoap::heuristic::{
+    DistanceHeuristic, FfHeuristic, HAddHeuristic, HMaxHeuristic, Heuristic, WeightedAStar,
+    WeightedDistanceHeuristic, ZeroHeuristic,
+};
+pub use dogoap::planner::{PlanOptions, PlanningStrategy};
+pub use dogoap::prelude::{Action, BudgetLimit, Compare, Datum, Goal, Key, LocalState, Mutator};
 
 pub use crate::{
     create_planner,
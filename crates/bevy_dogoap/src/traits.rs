@@ -47,20 +47,20 @@ impl fmt::Debug for dyn InserterComponent {
 /// // Used as a Mutator:
 /// assert_eq!(
 ///     Hunger::increase(1.0),
-///     Mutator::Increment("hunger".to_string(), Datum::F64(2.0))
+///     Mutator::Increment("hunger".into(), Datum::F64(2.0))
 /// );
 ///
 /// // Used as a Precondition:
 /// assert_eq!(
-///     Hunger::is_less(10.0),
-///     ("hunger".to_string(), Compare::LessThanEquals(Datum::F64(10.0)))
+///     Hunger::is_at_most(10.0),
+///     ("hunger".into(), Compare::LessThanEquals(Datum::F64(10.0)))
 /// )
 /// ```
 #[bevy_trait_query::queryable]
 #[reflect_trait]
 pub trait DatumComponent: Send + Sync {
-    /// Gets the string identifier for the datum.
-    fn field_key(&self) -> String;
+    /// Gets the [`Key`] of the datum.
+    fn field_key(&self) -> Key;
     /// Gets the underlying datum.
     fn field_value(&self) -> Datum;
 }
@@ -116,17 +116,68 @@ pub trait EnumDatum: Send + Sync {
 
 /// Internal trait implemented by `#[derive(DatumComponent)]`
 pub trait Precondition<T> {
-    /// Returns the string representation of this type and a comparison for the concept of `==`.
-    fn is(val: T) -> (String, Compare);
+    /// Returns the [`Key`] of this type and a comparison for the concept of `==`.
+    fn is(val: T) -> (Key, Compare);
 
-    /// Returns the string representation of this type and a comparison for the concept of `!=`.
-    fn is_not(val: T) -> (String, Compare);
+    /// Returns the [`Key`] of this type and a comparison for the concept of `!=`.
+    fn is_not(val: T) -> (Key, Compare);
 
-    /// Returns the string representation of this type and a comparison for the concept of `>`.
-    fn is_more(val: T) -> (String, Compare);
+    /// Returns the [`Key`] of this type and a comparison for the concept of `>=`, the same as
+    /// [`Precondition::is_at_least`].
+    #[deprecated(note = "use is_at_least, or is_more_than to leave out `val` itself")]
+    fn is_more(val: T) -> (Key, Compare) {
+        Self::is_at_least(val)
+    }
+
+    /// Returns the [`Key`] of this type and a comparison for the concept of `<=`, the same as
+    /// [`Precondition::is_at_most`].
+    #[deprecated(note = "use is_at_most, or is_less_than to leave out `val` itself")]
+    fn is_less(val: T) -> (Key, Compare) {
+        Self::is_at_most(val)
+    }
+
+    /// Returns the [`Key`] of this type and a comparison for the concept of `>`.
+    fn is_more_than(val: T) -> (Key, Compare);
+
+    /// Returns the [`Key`] of this type and a comparison for the concept of `<`.
+    fn is_less_than(val: T) -> (Key, Compare);
+
+    /// Returns the [`Key`] of this type and a comparison for the concept of `>=`.
+    fn is_at_least(val: T) -> (Key, Compare);
+
+    /// Returns the [`Key`] of this type and a comparison for the concept of `<=`.
+    fn is_at_most(val: T) -> (Key, Compare);
+
+    /// Returns the [`Key`] of this type and a comparison for being between `min` and `max`,
+    /// including both.
+    fn is_between(min: T, max: T) -> (Key, Compare);
+
+    /// Returns the [`Key`] of this type and a comparison for being equal to any of `vals`.
+    fn is_one_of(vals: impl IntoIterator<Item = T>) -> (Key, Compare);
+
+    /// Returns the [`Key`] of this type and a comparison for the concept of `==` with the value
+    /// of another key, see [`Compare::ToKey`].
+    fn is_key(key: impl Into<Key>) -> (Key, Compare);
+
+    /// Returns the [`Key`] of this type and a comparison for the concept of `!=` with the value
+    /// of another key.
+    fn is_not_key(key: impl Into<Key>) -> (Key, Compare);
+
+    /// Returns the [`Key`] of this type and a comparison for the concept of `>` with the value
+    /// of another key.
+    fn is_more_than_key(key: impl Into<Key>) -> (Key, Compare);
+
+    /// Returns the [`Key`] of this type and a comparison for the concept of `<` with the value
+    /// of another key.
+    fn is_less_than_key(key: impl Into<Key>) -> (Key, Compare);
+
+    /// Returns the [`Key`] of this type and a comparison for the concept of `>=` with the value
+    /// of another key.
+    fn is_at_least_key(key: impl Into<Key>) -> (Key, Compare);
 
-    /// Returns the string representation of this type and a comparison for the concept of `<`.
-    fn is_less(val: T) -> (String, Compare);
+    /// Returns the [`Key`] of this type and a comparison for the concept of `<=` with the value
+    /// of another key.
+    fn is_at_most_key(key: impl Into<Key>) -> (Key, Compare);
 }
 
 /// Internal trait implemented by `#[derive(DatumComponent)]` in order to mutate
@@ -137,4 +188,21 @@ pub trait MutatorTrait<T> {
     fn increase(val: T) -> Mutator;
     /// Returns a [`Mutator`] that decreases the value of the component by the given value.
     fn decrease(val: T) -> Mutator;
+    /// Returns a [`Mutator`] that multiplies the value of the component by the given value.
+    fn multiply(val: T) -> Mutator;
+    /// Returns a [`Mutator`] that divides the value of the component by the given value.
+    fn divide(val: T) -> Mutator;
+    /// Returns a [`Mutator`] that caps the value of the component at the given value.
+    fn min(val: T) -> Mutator;
+    /// Returns a [`Mutator`] that raises the value of the component to at least the given value.
+    fn max(val: T) -> Mutator;
+    /// Returns a [`Mutator`] that sets the value of the component to the value of another key.
+    fn copy_from(key: impl Into<Key>) -> Mutator;
+    /// Returns a [`Mutator`] that flips the value of a `bool` component.
+    fn toggle() -> Mutator;
+    /// Returns a [`Mutator`] that increases the value of the component by the value of another
+    /// key.
+    fn add_key(key: impl Into<Key>) -> Mutator;
+    /// Returns a [`Mutator`] that removes the component's key from the state.
+    fn remove() -> Mutator;
 }
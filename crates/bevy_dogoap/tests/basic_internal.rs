@@ -166,7 +166,7 @@ mod test {
     fn assert_key_is_bool(app: &mut App, key: &str, expected_bool: bool) {
         let state = get_state(app);
         let expected_val = Datum::Bool(expected_bool);
-        let found_val = state.data.get(key).unwrap();
+        let found_val = state.get(key).unwrap();
         assert_eq!(*found_val, expected_val);
     }
 
@@ -0,0 +1,188 @@
+//! Tests repairing a plan while one of its actions is still being executed
+
+use bevy::prelude::*;
+use bevy_dogoap::prelude::*;
+use bevy_platform::collections::HashMap;
+use dogoap::simple::simple_action;
+
+const IS_HUNGRY_KEY: &str = "is_hungry";
+const EAT_ACTION: &str = "eat_action";
+
+const IS_TIRED_KEY: &str = "is_tired";
+const SLEEP_ACTION: &str = "sleep_action";
+
+#[derive(Component, Clone, Reflect, Default, ActionComponent)]
+struct EatAction;
+
+#[derive(Component, Clone, Reflect, Default, ActionComponent)]
+struct SleepAction;
+
+#[derive(Component, Clone, DatumComponent)]
+struct IsHungry(bool);
+
+#[derive(Component, Clone, DatumComponent)]
+struct IsTired(bool);
+
+/// Sleeping only finishes once this is inserted
+#[derive(Resource)]
+struct WakeUp;
+
+/// How many times we've slept
+#[derive(Resource, Default)]
+struct TimesSlept(usize);
+
+fn startup(mut commands: Commands) {
+    let components = vec![
+        Box::new(IsHungry(true)) as Box<dyn DatumComponent>,
+        Box::new(IsTired(true)) as Box<dyn DatumComponent>,
+    ];
+
+    let goal = Goal::new()
+        .with_req(IS_HUNGRY_KEY, Compare::equals(false))
+        .with_req(IS_TIRED_KEY, Compare::equals(false));
+
+    // We need to sleep before we can eat, so the plan is always sleep, then eat
+    let eat_action = simple_action(EAT_ACTION, IS_HUNGRY_KEY, false)
+        .with_precondition((IS_TIRED_KEY, Compare::equals(false)));
+    let sleep_action = simple_action(SLEEP_ACTION, IS_TIRED_KEY, false);
+
+    let actions_map = HashMap::from([
+        (
+            EAT_ACTION.to_string(),
+            (
+                eat_action,
+                Box::new(EatAction) as Box<dyn InserterComponent>,
+            ),
+        ),
+        (
+            SLEEP_ACTION.to_string(),
+            (
+                sleep_action,
+                Box::new(SleepAction) as Box<dyn InserterComponent>,
+            ),
+        ),
+    ]);
+
+    let mut planner = Planner::new(components, vec![goal], actions_map);
+    planner.repair_plans = true;
+
+    commands
+        .spawn((planner, IsHungry(true), IsTired(true)))
+        .trigger(UpdatePlan::from);
+}
+
+fn handle_eat_action(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut IsHungry), With<EatAction>>,
+) {
+    for (entity, mut is_hungry) in query.iter_mut() {
+        is_hungry.0 = false;
+        commands.entity(entity).remove::<EatAction>();
+    }
+}
+
+fn handle_sleep_action(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut IsTired), With<SleepAction>>,
+    wake_up: Option<Res<WakeUp>>,
+    mut times_slept: ResMut<TimesSlept>,
+) {
+    if wake_up.is_none() {
+        return;
+    }
+    for (entity, mut is_tired) in query.iter_mut() {
+        is_tired.0 = false;
+        times_slept.0 += 1;
+        commands.entity(entity).remove::<SleepAction>();
+    }
+}
+
+mod test {
+    use std::time::Duration;
+
+    use bevy::time::TimeUpdateStrategy;
+    use bevy_log::LogPlugin;
+
+    use super::*;
+
+    #[derive(Resource)]
+    struct PlannerDone;
+
+    fn get_planner(app: &mut App) -> &Planner {
+        let mut query = app.world_mut().query::<&Planner>();
+        query.single(app.world()).unwrap()
+    }
+
+    fn wait_for_plan(app: &mut App) {
+        app.world_mut().remove_resource::<PlannerDone>();
+        loop {
+            // sleep because we're waiting for another thread to be done
+            std::thread::sleep(Duration::from_millis(50));
+            app.update();
+            if app.world_mut().get_resource::<PlannerDone>().is_some() {
+                break;
+            }
+        }
+    }
+
+    fn plan_actions(app: &mut App) -> Vec<String> {
+        let planner = get_planner(app);
+        let plan = planner.current_plan.as_ref().unwrap();
+        plan.steps.iter().map(|step| step.action.clone()).collect()
+    }
+
+    #[test]
+    fn test_repair_while_action_in_flight() {
+        let mut app = App::new();
+
+        app.add_plugins((
+            MinimalPlugins,
+            LogPlugin::default(),
+            DogoapPlugin::default(),
+        ))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(
+            // make every `app.update()` trigger a fixed loop
+            Time::<Fixed>::default().timestep(),
+        ))
+        .init_resource::<TimesSlept>()
+        .register_component_as::<dyn DatumComponent, IsHungry>()
+        .register_component_as::<dyn DatumComponent, IsTired>()
+        .register_component_as::<dyn ActionComponent, EatAction>()
+        .register_component_as::<dyn ActionComponent, SleepAction>()
+        .add_systems(Startup, startup)
+        .add_systems(FixedUpdate, (handle_eat_action, handle_sleep_action))
+        .add_observer(|_: On<Remove, IsPlanning>, mut commands: Commands| {
+            commands.insert_resource(PlannerDone);
+        });
+
+        app.finish();
+
+        wait_for_plan(&mut app);
+        app.update();
+        app.update();
+
+        // We're asleep, and ask for a new plan before waking up
+        let mut query = app
+            .world_mut()
+            .query_filtered::<Entity, With<SleepAction>>();
+        let entity = query.single(app.world()).unwrap();
+        assert_eq!(0, get_planner(&mut app).current_step);
+        app.world_mut().trigger(UpdatePlan::from(entity));
+        wait_for_plan(&mut app);
+
+        // The repaired plan still has the sleep that's running, and we're still on it
+        assert_eq!(vec![SLEEP_ACTION, EAT_ACTION], plan_actions(&mut app));
+        assert_eq!(0, get_planner(&mut app).current_step);
+
+        app.insert_resource(WakeUp);
+        for _ in 0..4 {
+            app.update();
+        }
+
+        let state = get_planner(&mut app).state.clone();
+        assert_eq!(Some(&Datum::Bool(false)), state.get(IS_HUNGRY_KEY));
+        assert_eq!(Some(&Datum::Bool(false)), state.get(IS_TIRED_KEY));
+        assert_eq!(1, app.world().resource::<TimesSlept>().0);
+        assert!(get_planner(&mut app).current_plan.is_none());
+    }
+}
@@ -3,22 +3,20 @@
 
 use criterion::{Criterion, black_box, criterion_group, criterion_main};
 use dogoap::{
-    planner::{PlanningStrategy, make_plan_with_strategy},
+    heuristic::{FfHeuristic, HAddHeuristic, HMaxHeuristic},
+    planner::{
+        CompiledDomain, PlanOptions, PlanningContext, PlanningStrategy, make_plan_with_options,
+    },
     prelude::*,
     simple::{simple_decrement_action, simple_increment_action},
 };
 
-fn long_plan(planning_strategy: PlanningStrategy) {
+fn long_plan_scenario() -> (LocalState, Vec<Action>, Goal) {
     let start = LocalState::new()
         .with_datum("energy", 30_i64)
         .with_datum("hunger", 70_i64)
         .with_datum("gold", 0_i64);
 
-    let expected_state = LocalState::new()
-        .with_datum("energy", 50_i64)
-        .with_datum("hunger", 50_i64)
-        .with_datum("gold", 7_i64);
-
     let goal = Goal::new().with_req("gold", Compare::equals(7_i64));
 
     let sleep_action = simple_increment_action("sleep", "energy", 10_i64);
@@ -34,25 +32,119 @@ fn long_plan(planning_strategy: PlanningStrategy) {
                 Mutator::increment("hunger", 5_i64),
             ],
             state: LocalState::default(),
-            cost: 1,
+            cost: Cost::ONE,
+            cost_fn: None,
         })
         .with_precondition(("hunger", Compare::less_than_equals(50_i64)))
         .with_precondition(("energy", Compare::greater_than_equals(50_i64)));
 
     let actions: Vec<Action> = vec![sleep_action, eat_action, rob_people];
+    (start, actions, goal)
+}
+
+fn long_plan(options: &PlanOptions) {
+    let (start, actions, goal) = long_plan_scenario();
+    let expected_state = LocalState::new()
+        .with_datum("energy", 50_i64)
+        .with_datum("hunger", 50_i64)
+        .with_datum("gold", 7_i64);
 
-    let plan = make_plan_with_strategy(planning_strategy, &start, &actions[..], &goal);
-    let effects = get_effects_from_plan(plan.clone().unwrap().0).collect::<Vec<_>>();
+    let plan = make_plan_with_options(&start, &actions[..], &goal, options);
+    let effects = get_effects_from_plan(plan.clone().unwrap()).collect::<Vec<_>>();
 
     assert_eq!(11, effects.len());
     assert_eq!(expected_state, effects.last().unwrap().state);
 }
 
+/// Like [`long_plan`], but every action only changes its key by 1, so the plan is a lot longer and
+/// the heuristic has a lot more to go on
+fn longer_plan(options: &PlanOptions) {
+    let start = LocalState::new()
+        .with_datum("energy", 30_i64)
+        .with_datum("hunger", 70_i64)
+        .with_datum("gold", 0_i64);
+
+    let expected_state = LocalState::new()
+        .with_datum("energy", 50_i64)
+        .with_datum("hunger", 50_i64)
+        .with_datum("gold", 10_i64);
+
+    let goal = Goal::new().with_req("gold", Compare::equals(10_i64));
+
+    let sleep_action = simple_increment_action("sleep", "energy", 1_i64);
+
+    let eat_action = simple_decrement_action("eat", "hunger", 1_i64)
+        .with_precondition(("energy", Compare::greater_than_equals(50_i64)));
+
+    let rob_people = simple_increment_action("rob", "gold", 1_i64)
+        .with_precondition(("hunger", Compare::less_than_equals(50_i64)))
+        .with_precondition(("energy", Compare::greater_than_equals(50_i64)));
+
+    let actions: Vec<Action> = vec![sleep_action, eat_action, rob_people];
+
+    let plan = make_plan_with_options(&start, &actions[..], &goal, options);
+    let effects = get_effects_from_plan(plan.unwrap()).collect::<Vec<_>>();
+
+    assert_eq!(50, effects.len());
+    assert_eq!(expected_state, effects.last().unwrap().state);
+}
+
 fn bench_start_to_goal_strategy(c: &mut Criterion) {
+    let options = PlanOptions::new();
     c.bench_function("Start To Goal", |b| {
-        b.iter(|| long_plan(black_box(PlanningStrategy::StartToGoal)));
+        b.iter(|| long_plan(black_box(&options)));
+    });
+}
+
+fn bench_goal_to_start_strategy(c: &mut Criterion) {
+    let options = PlanOptions::new().with_strategy(PlanningStrategy::GoalToStart);
+    c.bench_function("Goal To Start", |b| {
+        b.iter(|| long_plan(black_box(&options)));
+    });
+}
+
+fn bench_relaxed_heuristics(c: &mut Criterion) {
+    let heuristics = [
+        ("h_max", PlanOptions::new().with_heuristic(HMaxHeuristic)),
+        ("h_add", PlanOptions::new().with_heuristic(HAddHeuristic)),
+        ("FF", PlanOptions::new().with_heuristic(FfHeuristic)),
+    ];
+    for (name, options) in &heuristics {
+        c.bench_function(&format!("Start To Goal ({name})"), |b| {
+            b.iter(|| long_plan(black_box(options)));
+        });
+    }
+
+    let options = PlanOptions::new();
+    c.bench_function("Longer Plan", |b| {
+        b.iter(|| longer_plan(black_box(&options)));
+    });
+    for (name, options) in &heuristics {
+        c.bench_function(&format!("Longer Plan ({name})"), |b| {
+            b.iter(|| longer_plan(black_box(options)));
+        });
+    }
+}
+
+fn bench_planning_context(c: &mut Criterion) {
+    let (start, actions, goal) = long_plan_scenario();
+    let domain = CompiledDomain::new(&actions, &goal);
+    let options = PlanOptions::new();
+    let mut context = PlanningContext::new();
+    c.bench_function("Start To Goal (Reused Context)", |b| {
+        b.iter(|| {
+            domain
+                .make_plan_with_context(black_box(&start), &options, &mut context)
+                .unwrap()
+        });
     });
 }
 
-criterion_group!(benches, bench_start_to_goal_strategy);
+criterion_group!(
+    benches,
+    bench_start_to_goal_strategy,
+    bench_planning_context,
+    bench_goal_to_start_strategy,
+    bench_relaxed_heuristics
+);
 criterion_main!(benches);
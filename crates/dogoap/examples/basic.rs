@@ -12,11 +12,14 @@ fn main() {
     let eat_action = Action {
         key: "eat".to_string(),
         preconditions: vec![],
+        conditions: vec![],
+        precondition_fns: vec![],
         effects: vec![Effect {
             action: "eat".to_string(),
             mutators: vec![Mutator::set("is_hungry", false)],
             state: LocalState::new(),
-            cost: 1,
+            cost: Cost::ONE,
+            cost_fn: None,
         }],
     };
 
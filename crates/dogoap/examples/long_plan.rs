@@ -32,7 +32,8 @@ fn main() {
                 Mutator::increment("hunger", 5_i64),
             ],
             state: LocalState::default(),
-            cost: 1,
+            cost: Cost::ONE,
+            cost_fn: None,
         })
         .with_precondition(("hunger", Compare::less_than_equals(50_i64)))
         .with_precondition(("energy", Compare::greater_than_equals(50_i64)));
@@ -40,7 +41,7 @@ fn main() {
     let actions: Vec<Action> = vec![sleep_action, eat_action, rob_people];
 
     let plan = make_plan(&start, &actions[..], &goal);
-    let effects = get_effects_from_plan(plan.clone().unwrap().0).collect::<Vec<_>>();
+    let effects = get_effects_from_plan(plan.clone().unwrap()).collect::<Vec<_>>();
     assert_eq!(11, effects.len());
 
     println!("{}", format_plan(plan.clone().unwrap()));
@@ -1,8 +1,12 @@
 // crate "dogoap" file action.rs
 use std::hash::{Hash, Hasher};
 
-use crate::compare::Compare;
+use crate::compare::{Compare, PreconditionFn};
+use crate::condition::Condition;
+use crate::cost::{Cost, CostFn};
 use crate::effect::Effect;
+use crate::key::Key;
+use crate::localstate::LocalState;
 use crate::mutator::Mutator;
 
 /// An `Action` represents something your Entity can do, granted the `LocalState`
@@ -16,7 +20,17 @@ pub struct Action {
     // TODO arguments coupled with Effects, maybe
     // pub argument: Option<Datum>,
     /// What preconditions need to be true before we can execute this action
-    pub preconditions: Vec<(String, Compare)>,
+    pub preconditions: Vec<(Key, Compare)>,
+    /// Preconditions that combine comparisons with `And`, `Or` and `Not`, on top of
+    /// [`Self::preconditions`]
+    pub conditions: Vec<Condition>,
+    /// Preconditions that are checked by closures, on top of [`Self::preconditions`]
+    ///
+    /// [`PlanningStrategy::GoalToStart`](crate::planner::PlanningStrategy::GoalToStart) can't
+    /// check them without knowing the state, so when any [`Action`] has them, plans are always
+    /// searched for from the start.
+    #[cfg_attr(feature = "bevy_reflect", reflect(ignore))]
+    pub precondition_fns: Vec<PreconditionFn>,
     /// What is the outcome from doing this action
     // TODO temporarily plural effects, as maybe we want to implement arguments with many effects...
     pub effects: Vec<Effect>,
@@ -26,6 +40,8 @@ impl Hash for Action {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.key.hash(state);
         self.preconditions.hash(state);
+        self.conditions.hash(state);
+        self.precondition_fns.hash(state);
         self.effects.hash(state);
     }
 }
@@ -36,6 +52,8 @@ impl Action {
         Self {
             key: key.into(),
             preconditions: vec![],
+            conditions: vec![],
+            precondition_fns: vec![],
             effects: vec![],
         }
     }
@@ -47,11 +65,27 @@ impl Action {
     }
 
     /// Add a precondition to the action, i.e. something that must be true before the action can be executed.
-    pub fn with_precondition(mut self, (key, compare): (impl Into<String>, Compare)) -> Self {
+    pub fn with_precondition(mut self, (key, compare): (impl Into<Key>, Compare)) -> Self {
         self.preconditions.push((key.into(), compare));
         self
     }
 
+    /// Add a precondition that combines comparisons, see [`Condition`].
+    pub fn with_condition(mut self, condition: impl Into<Condition>) -> Self {
+        self.conditions.push(condition.into());
+        self
+    }
+
+    /// Add a precondition that is checked by a closure, see [`PreconditionFn`].
+    pub fn with_precondition_fn(
+        mut self,
+        name: impl Into<String>,
+        check: impl Fn(&LocalState) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.precondition_fns.push(PreconditionFn::new(name, check));
+        self
+    }
+
     // TODO currently only handles one effect
     /// Adds a mutator to the action's effect, i.e. something that will be mutated after the action is executed.
     /// An effect can have an arbritrary nonzero amount of mutators.
@@ -67,9 +101,25 @@ impl Action {
     }
 
     /// Set the effect's cost.
-    pub fn set_cost(mut self, new_cost: usize) -> Self {
+    pub fn set_cost(mut self, new_cost: impl Into<Cost>) -> Self {
+        let mut effect = self.effects[0].clone();
+        effect.cost = new_cost.into();
+        self.effects[0] = effect;
+        self
+    }
+
+    /// Makes the effect's cost depend on the state it's applied to, see [`Effect::cost_fn`].
+    ///
+    /// Resets the static cost to 0, as the planner uses it as the least the effect can cost. If
+    /// you know a higher lower bound, call [`Action::set_cost`] afterwards, which helps the
+    /// heuristics.
+    pub fn set_cost_fn<C: Into<Cost>>(
+        mut self,
+        cost_fn: impl Fn(&LocalState) -> C + Send + Sync + 'static,
+    ) -> Self {
         let mut effect = self.effects[0].clone();
-        effect.cost = new_cost;
+        effect.cost = Cost::ZERO;
+        effect.cost_fn = Some(CostFn::new(cost_fn));
         self.effects[0] = effect;
         self
     }
@@ -0,0 +1,215 @@
+//! Finding the `k` cheapest plans instead of only the cheapest one
+//!
+//! Uses Yen's algorithm: every plan after the first one takes a prefix of a plan found before,
+//! and searches for the cheapest way to the [`Goal`](crate::goal::Goal) that leaves it with a
+//! different action than any plan found so far with the same prefix. States of the prefix can't
+//! be visited again, so plans never go in circles.
+
+use crate::{
+    cost::Cost,
+    domain::{CompiledDomain, ForwardSearch, State},
+    error::PlanError,
+    localstate::LocalState,
+    planner::{Plan, PlanOptions, replay_plan},
+    search::{SearchBuffers, SearchSpace, Successor, search},
+};
+
+/// A plan that was found, in terms of the compiled domain
+struct Candidate {
+    /// Index of the action taken at each step
+    actions: Vec<usize>,
+    /// The start state and the state after each step
+    states: Vec<State>,
+    cost: Cost,
+}
+
+/// Searches from a state of a plan that was found before, with the ways to go on from there that
+/// were already taken forbidden
+struct SpurSearch<'a> {
+    forward: &'a ForwardSearch<'a>,
+    /// The state the search starts from
+    spur: &'a State,
+    /// Actions that can't be taken from `spur`
+    forbidden_actions: Vec<usize>,
+    /// States of the prefix leading to `spur`
+    forbidden_states: &'a [State],
+}
+
+impl SearchSpace for SpurSearch<'_> {
+    type Node = State;
+
+    fn successors(
+        &self,
+        node: &State,
+        successors: &mut Vec<Successor<State>>,
+    ) -> Result<(), PlanError> {
+        let start = successors.len();
+        self.forward.successors(node, successors)?;
+        let from_spur = node == self.spur;
+        let mut index = start;
+        while index < successors.len() {
+            let successor = &successors[index];
+            if (from_spur && self.forbidden_actions.contains(&successor.action))
+                || self.forbidden_states.contains(&successor.node)
+            {
+                let removed = successors.swap_remove(index);
+                self.forward.recycle(removed.node);
+            } else {
+                index += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn heuristic(&self, node: &State) -> Cost {
+        self.forward.heuristic(node)
+    }
+
+    fn is_goal(&self, node: &State) -> Result<bool, PlanError> {
+        self.forward.is_goal(node)
+    }
+
+    fn recycle(&self, node: State) {
+        self.forward.recycle(node);
+    }
+}
+
+/// Follows `actions` from `start`, collecting the states along the way
+fn states_along(
+    domain: &CompiledDomain,
+    start: &State,
+    actions: &[usize],
+    scratch: &mut LocalState,
+) -> Result<Vec<State>, PlanError> {
+    let mut states = vec![start.clone()];
+    for index in actions {
+        let action = domain
+            .compiled_actions
+            .iter()
+            .find(|action| action.action == *index)
+            .expect("The search only takes compiled actions");
+        let state = states.last().expect("There is always the start state");
+        let next = domain
+            .successor(state, action, &mut Vec::new(), scratch)?
+            .expect("The search only takes actions whose preconditions pass");
+        states.push(next);
+    }
+    Ok(states)
+}
+
+/// The cost of taking `actions` from the `states` before each of them. `scratch` is the start
+/// state, see [`CompiledDomain::action_cost`].
+fn path_cost(
+    domain: &CompiledDomain,
+    states: &[State],
+    actions: &[usize],
+    scratch: &mut LocalState,
+) -> Cost {
+    actions
+        .iter()
+        .zip(states)
+        .map(|(&action, state)| domain.action_cost(action, state, scratch))
+        .sum()
+}
+
+pub(crate) fn make_plans(
+    domain: &CompiledDomain,
+    start: &LocalState,
+    options: &PlanOptions,
+    k: usize,
+) -> Result<Vec<Plan>, PlanError> {
+    let start_state = domain.state_from(start);
+    if domain.is_goal(&start_state.values)? {
+        return Err(PlanError::GoalAlreadySatisfied);
+    }
+    if k == 0 {
+        return Ok(Vec::new());
+    }
+
+    let forward = ForwardSearch::new(domain, start, options, Vec::new());
+    let mut buffers = SearchBuffers::default();
+    let path = search(&forward, start_state.clone(), options, false, &mut buffers)?;
+    let mut scratch = start.clone();
+    let states = states_along(domain, &start_state, &path.actions, &mut scratch)?;
+    let mut found = vec![Candidate {
+        cost: path_cost(domain, &states, &path.actions, &mut scratch),
+        states,
+        actions: path.actions,
+    }];
+    let mut candidates: Vec<Candidate> = Vec::new();
+
+    while found.len() < k {
+        let last = found
+            .last()
+            .expect("The cheapest plan is always found first");
+        for spur in 0..last.actions.len() {
+            let root = &last.actions[..spur];
+            let forbidden_actions = found
+                .iter()
+                .filter(|plan| plan.actions.len() > spur && plan.actions[..spur] == *root)
+                .map(|plan| plan.actions[spur])
+                .collect();
+            let space = SpurSearch {
+                forward: &forward,
+                spur: &last.states[spur],
+                forbidden_actions,
+                forbidden_states: &last.states[..spur],
+            };
+            let root_cost = path_cost(domain, &last.states, root, &mut scratch);
+            let mut spur_options = options.clone();
+            spur_options.max_plan_length =
+                options.max_plan_length.map(|max| max.saturating_sub(spur));
+            spur_options.max_cost = options
+                .max_cost
+                .map(|max| (max - root_cost).max(Cost::ZERO));
+            let path = match search(
+                &space,
+                last.states[spur].clone(),
+                &spur_options,
+                false,
+                &mut buffers,
+            ) {
+                Ok(path) => path,
+                // There just isn't another way from this state
+                Err(PlanError::SearchExhausted | PlanError::BudgetExceeded(_)) => continue,
+                Err(err) => return Err(err),
+            };
+            let mut actions = root.to_vec();
+            actions.extend(path.actions);
+            if found
+                .iter()
+                .chain(&candidates)
+                .any(|plan| plan.actions == actions)
+            {
+                continue;
+            }
+            let mut states = last.states[..spur].to_vec();
+            states.extend(states_along(
+                domain,
+                &last.states[spur],
+                &actions[spur..],
+                &mut scratch,
+            )?);
+            candidates.push(Candidate {
+                cost: path_cost(domain, &states, &actions, &mut scratch),
+                actions,
+                states,
+            });
+        }
+        // The first of the cheapest candidates, so ties keep the order they were found in
+        let Some(cheapest) = candidates
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, candidate)| candidate.cost)
+            .map(|(index, _)| index)
+        else {
+            break;
+        };
+        found.push(candidates.remove(cheapest));
+    }
+
+    found
+        .into_iter()
+        .map(|plan| replay_plan(start, domain.actions(), domain.goal(), plan.actions))
+        .collect()
+}
@@ -1,9 +1,12 @@
-use crate::{action::Action, datum::Datum, localstate::LocalState};
+use crate::{datum::Datum, error::PlanError, key::Key, localstate::LocalState};
+use std::borrow::Cow;
+use std::fmt::{self, Debug};
 use std::hash::{Hash, Hasher};
+use std::sync::Arc;
 
 /// Allows you to Compare [`Datum`] between each other. Used for Preconditions
 /// and in path finding until we reach our goal.
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
 pub enum Compare {
     /// Checks if two [`Datum`] are equal.
@@ -14,6 +17,62 @@ pub enum Compare {
     GreaterThanEquals(Datum),
     /// Checks if the first [`Datum`] is less than or equal to the second [`Datum`].
     LessThanEquals(Datum),
+    /// Checks if the first [`Datum`] is greater than the second [`Datum`].
+    GreaterThan(Datum),
+    /// Checks if the first [`Datum`] is less than the second [`Datum`].
+    LessThan(Datum),
+    /// Checks if the [`Datum`] is between the two [`Datum`]s, including both.
+    InRange(Datum, Datum),
+    /// Checks if the [`Datum`] is equal to any of the [`Datum`]s, like being at one of several
+    /// locations. Never passes when there are none.
+    OneOf(Vec<Datum>),
+    /// Checks the [`Datum`] against the value of another key in the same [`LocalState`], like
+    /// having at least as much gold as the price. See [`Compare::resolve`].
+    ToKey(Relation, Key),
+}
+
+/// How a [`Compare::ToKey`] compares against the value of the other key
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
+pub enum Relation {
+    /// Like [`Compare::Equals`]
+    Equals,
+    /// Like [`Compare::NotEquals`]
+    NotEquals,
+    /// Like [`Compare::GreaterThanEquals`]
+    GreaterThanEquals,
+    /// Like [`Compare::LessThanEquals`]
+    LessThanEquals,
+    /// Like [`Compare::GreaterThan`]
+    GreaterThan,
+    /// Like [`Compare::LessThan`]
+    LessThan,
+}
+
+impl Relation {
+    /// The [`Compare`] that checks this relation against `value`
+    pub fn compare(self, value: Datum) -> Compare {
+        match self {
+            Relation::Equals => Compare::Equals(value),
+            Relation::NotEquals => Compare::NotEquals(value),
+            Relation::GreaterThanEquals => Compare::GreaterThanEquals(value),
+            Relation::LessThanEquals => Compare::LessThanEquals(value),
+            Relation::GreaterThan => Compare::GreaterThan(value),
+            Relation::LessThan => Compare::LessThan(value),
+        }
+    }
+
+    /// The relation that holds exactly when this one doesn't
+    pub fn negate(self) -> Relation {
+        match self {
+            Relation::Equals => Relation::NotEquals,
+            Relation::NotEquals => Relation::Equals,
+            Relation::GreaterThanEquals => Relation::LessThan,
+            Relation::LessThanEquals => Relation::GreaterThan,
+            Relation::GreaterThan => Relation::LessThanEquals,
+            Relation::LessThan => Relation::GreaterThanEquals,
+        }
+    }
 }
 
 impl Compare {
@@ -36,16 +95,125 @@ impl Compare {
     pub fn less_than_equals(value: impl Into<Datum>) -> Self {
         Compare::LessThanEquals(value.into())
     }
+
+    /// Convenience method for creating a [`Compare::GreaterThan`]
+    pub fn greater_than(value: impl Into<Datum>) -> Self {
+        Compare::GreaterThan(value.into())
+    }
+
+    /// Convenience method for creating a [`Compare::LessThan`]
+    pub fn less_than(value: impl Into<Datum>) -> Self {
+        Compare::LessThan(value.into())
+    }
+
+    /// Convenience method for creating a [`Compare::InRange`]
+    pub fn in_range(min: impl Into<Datum>, max: impl Into<Datum>) -> Self {
+        Compare::InRange(min.into(), max.into())
+    }
+
+    /// Convenience method for creating a [`Compare::OneOf`]
+    pub fn one_of(values: impl IntoIterator<Item = impl Into<Datum>>) -> Self {
+        Compare::OneOf(values.into_iter().map(Into::into).collect())
+    }
+
+    /// Convenience method for creating a [`Compare::ToKey`] with [`Relation::Equals`]
+    pub fn equals_key(key: impl Into<Key>) -> Self {
+        Compare::ToKey(Relation::Equals, key.into())
+    }
+
+    /// Convenience method for creating a [`Compare::ToKey`] with [`Relation::NotEquals`]
+    pub fn not_equals_key(key: impl Into<Key>) -> Self {
+        Compare::ToKey(Relation::NotEquals, key.into())
+    }
+
+    /// Convenience method for creating a [`Compare::ToKey`] with [`Relation::GreaterThanEquals`]
+    pub fn greater_than_equals_key(key: impl Into<Key>) -> Self {
+        Compare::ToKey(Relation::GreaterThanEquals, key.into())
+    }
+
+    /// Convenience method for creating a [`Compare::ToKey`] with [`Relation::LessThanEquals`]
+    pub fn less_than_equals_key(key: impl Into<Key>) -> Self {
+        Compare::ToKey(Relation::LessThanEquals, key.into())
+    }
+
+    /// Convenience method for creating a [`Compare::ToKey`] with [`Relation::GreaterThan`]
+    pub fn greater_than_key(key: impl Into<Key>) -> Self {
+        Compare::ToKey(Relation::GreaterThan, key.into())
+    }
+
+    /// Convenience method for creating a [`Compare::ToKey`] with [`Relation::LessThan`]
+    pub fn less_than_key(key: impl Into<Key>) -> Self {
+        Compare::ToKey(Relation::LessThan, key.into())
+    }
 }
 
 impl Compare {
-    /// Gets the [`Datum`] that we are comparing against.
-    pub fn value(&self) -> Datum {
+    /// Gets the [`Datum`]s that we are comparing against, in order. Both bounds of a
+    /// [`Compare::InRange`], and none for an empty [`Compare::OneOf`] or a [`Compare::ToKey`].
+    pub fn values(&self) -> impl Iterator<Item = Datum> + '_ {
+        let (pair, list): ([Option<Datum>; 2], &[Datum]) = match self {
+            Compare::Equals(value)
+            | Compare::NotEquals(value)
+            | Compare::GreaterThanEquals(value)
+            | Compare::LessThanEquals(value)
+            | Compare::GreaterThan(value)
+            | Compare::LessThan(value) => ([Some(*value), None], &[]),
+            Compare::InRange(min, max) => ([Some(*min), Some(*max)], &[]),
+            Compare::OneOf(values) => ([None, None], values),
+            Compare::ToKey(..) => ([None, None], &[]),
+        };
+        pair.into_iter().flatten().chain(list.iter().copied())
+    }
+
+    /// The same comparison, with every [`Datum`] replaced by what `f` returns for it
+    pub(crate) fn try_map(
+        &self,
+        mut f: impl FnMut(Datum) -> Result<Datum, PlanError>,
+    ) -> Result<Compare, PlanError> {
+        Ok(match self {
+            Compare::Equals(value) => Compare::Equals(f(*value)?),
+            Compare::NotEquals(value) => Compare::NotEquals(f(*value)?),
+            Compare::GreaterThanEquals(value) => Compare::GreaterThanEquals(f(*value)?),
+            Compare::LessThanEquals(value) => Compare::LessThanEquals(f(*value)?),
+            Compare::GreaterThan(value) => Compare::GreaterThan(f(*value)?),
+            Compare::LessThan(value) => Compare::LessThan(f(*value)?),
+            Compare::InRange(min, max) => Compare::InRange(f(*min)?, f(*max)?),
+            Compare::OneOf(values) => Compare::OneOf(
+                values
+                    .iter()
+                    .map(|value| f(*value))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Compare::ToKey(relation, key) => Compare::ToKey(*relation, *key),
+        })
+    }
+
+    /// The other key a [`Compare::ToKey`] compares against
+    pub fn other_key(&self) -> Option<Key> {
         match self {
-            Compare::Equals(f)
-            | Compare::NotEquals(f)
-            | Compare::GreaterThanEquals(f)
-            | Compare::LessThanEquals(f) => *f,
+            Compare::ToKey(_, key) => Some(*key),
+            _ => None,
+        }
+    }
+
+    /// Replaces a [`Compare::ToKey`] with the comparison against the value of its key in `state`,
+    /// so it can be checked against a value on its own. Other comparisons stay as they are.
+    /// Returns [`PlanError::MissingKey`] if `state` doesn't have the key.
+    pub fn resolve(&self, state: &LocalState) -> Result<Cow<'_, Compare>, PlanError> {
+        self.resolve_with(|key| state.data.get(&key).copied())
+    }
+
+    /// Like [`Compare::resolve`], with `value_of` looking up the value of the key
+    pub(crate) fn resolve_with(
+        &self,
+        value_of: impl FnOnce(Key) -> Option<Datum>,
+    ) -> Result<Cow<'_, Compare>, PlanError> {
+        match self {
+            Compare::ToKey(relation, key) => {
+                let value = value_of(*key).ok_or(PlanError::MissingKey(*key))?;
+                Ok(Cow::Owned(relation.compare(value)))
+            }
+            _ => Ok(Cow::Borrowed(self)),
         }
     }
 }
@@ -69,43 +237,203 @@ impl Hash for Compare {
                 3_u8.hash(state);
                 datum.hash(state);
             }
+            Compare::GreaterThan(datum) => {
+                4_u8.hash(state);
+                datum.hash(state);
+            }
+            Compare::LessThan(datum) => {
+                5_u8.hash(state);
+                datum.hash(state);
+            }
+            Compare::InRange(min, max) => {
+                6_u8.hash(state);
+                min.hash(state);
+                max.hash(state);
+            }
+            Compare::OneOf(data) => {
+                7_u8.hash(state);
+                data.hash(state);
+            }
+            Compare::ToKey(relation, key) => {
+                8_u8.hash(state);
+                relation.hash(state);
+                key.hash(state);
+            }
+        }
+    }
+}
+
+/// A precondition that is checked by a closure, for what can't be expressed by comparing a key
+/// against a [`Datum`], like whether there is a reachable ore node, or whether energy is at least
+/// twice the hunger. Add it with
+/// [`Action::with_precondition_fn`](crate::action::Action::with_precondition_fn).
+///
+/// The name shows up in [`Debug`] output and when
+/// [`simulate_plan`](crate::planner::simulate_plan) finds that it doesn't hold. Two
+/// [`PreconditionFn`]s are only equal when they are clones of each other.
+#[derive(Clone)]
+pub struct PreconditionFn {
+    name: String,
+    check: Arc<dyn Fn(&LocalState) -> bool + Send + Sync>,
+}
+
+impl PreconditionFn {
+    /// Creates a new [`PreconditionFn`] called `name`, which holds when `check` returns `true`
+    pub fn new(
+        name: impl Into<String>,
+        check: impl Fn(&LocalState) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            check: Arc::new(check),
         }
     }
+
+    /// The name this precondition was created with
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether the precondition holds in `state`
+    pub fn check(&self, state: &LocalState) -> bool {
+        (self.check)(state)
+    }
+}
+
+impl PartialEq for PreconditionFn {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && Arc::ptr_eq(&self.check, &other.check)
+    }
+}
+
+impl Eq for PreconditionFn {}
+
+/// Only hashes the name, as the closure can't be hashed
+impl Hash for PreconditionFn {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+    }
 }
 
-pub fn compare_values(comparison: &Compare, value: &Datum) -> bool {
-    match comparison {
+impl Debug for PreconditionFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("PreconditionFn").field(&self.name).finish()
+    }
+}
+
+/// Checks `value` against `comparison`.
+/// Returns [`PlanError::TypeMismatch`] if the two [`Datum`]s are of different types.
+///
+/// The value of the other key of a [`Compare::ToKey`] isn't known here, so it needs to be
+/// [resolved](Compare::resolve) first, like [`compare_key`] does. Otherwise this returns
+/// [`PlanError::MissingKey`] for that key.
+pub fn compare_values(comparison: &Compare, value: &Datum) -> Result<bool, PlanError> {
+    if let Some(other) = comparison.values().find(|other| !value.same_type(other)) {
+        return Err(value.mismatch(&other));
+    }
+    Ok(match comparison {
         Compare::Equals(v) => value == v,
         Compare::NotEquals(v) => value != v,
         Compare::GreaterThanEquals(v) => value >= v,
         Compare::LessThanEquals(v) => value <= v,
-    }
+        Compare::GreaterThan(v) => value > v,
+        Compare::LessThan(v) => value < v,
+        Compare::InRange(min, max) => min <= value && value <= max,
+        Compare::OneOf(values) => values.contains(value),
+        Compare::ToKey(_, key) => return Err(PlanError::MissingKey(*key)),
+    })
 }
 
-/// Checks all the preconditions from the `Action` against passed in `LocalState`
-/// Returns `true` if all the preconditions pass (or if there is none), otherwise `false`
-pub fn check_preconditions(state: &LocalState, action: &Action) -> bool {
-    action.preconditions.iter().all(|(key, value)| {
-        let state_value = state
-            .data
-            .get(key)
-            .unwrap_or_else(|| panic!("Couldn't find key {key:#?} in LocalState"));
-        compare_values(value, state_value)
+/// How far `value` is from passing `comparison`. Zero when it passes, otherwise the gap to the
+/// closest value that would pass. Integers are at least 1 away, as are `bool`s, enums,
+/// [`Compare::NotEquals`] and an empty [`Compare::OneOf`] when they don't pass. Floats are as far
+/// away as they are, without being rounded to a whole number.
+/// Returns [`PlanError::TypeMismatch`] if the two [`Datum`]s are of different types. Like with
+/// [`compare_values`], a [`Compare::ToKey`] needs to be [resolved](Compare::resolve) first.
+pub fn compare_distance(comparison: &Compare, value: &Datum) -> Result<f64, PlanError> {
+    if compare_values(comparison, value)? {
+        return Ok(0.0);
+    }
+    let distance = match comparison {
+        Compare::NotEquals(_) | Compare::ToKey(..) => 1.0,
+        Compare::Equals(v) | Compare::GreaterThanEquals(v) | Compare::LessThanEquals(v) => {
+            gap(value, v)?
+        }
+        // The closest integer is one past the bound, floats can get arbitrarily close to it
+        Compare::GreaterThan(v) | Compare::LessThan(v) => {
+            gap(value, v)? + if matches!(v, Datum::I64(_)) { 1.0 } else { 0.0 }
+        }
+        Compare::InRange(min, _) if value < min => gap(value, min)?,
+        Compare::InRange(_, max) => gap(value, max)?,
+        Compare::OneOf(values) => values
+            .iter()
+            .try_fold(None, |closest: Option<f64>, v| {
+                let distance = gap(value, v)?;
+                Ok::<_, PlanError>(Some(
+                    closest.map_or(distance, |closest| closest.min(distance)),
+                ))
+            })?
+            .unwrap_or(1.0),
+    };
+    Ok(match value {
+        // A float right at a strict bound still needs to move, if only a little
+        Datum::F64(_) => distance.max(f64::MIN_POSITIVE),
+        _ => distance.max(1.0),
     })
 }
 
+/// Like [`Datum::try_distance`], but without rounding the gap between floats down
+fn gap(value: &Datum, other: &Datum) -> Result<f64, PlanError> {
+    match (value, other) {
+        (Datum::F64(a), Datum::F64(b)) => Ok((a - b).abs()),
+        _ => Ok(value.try_distance(other)? as f64),
+    }
+}
+
+/// Checks `key` in `state` against `comparison`, see [`compare_values`]. A [`Compare::ToKey`]
+/// is checked against the value of its key in `state`.
+/// Returns [`PlanError::MissingKey`] if `state` doesn't have either key.
+pub fn compare_key(state: &LocalState, key: Key, comparison: &Compare) -> Result<bool, PlanError> {
+    let value = state.data.get(&key).ok_or(PlanError::MissingKey(key))?;
+    let comparison = comparison.resolve(state)?;
+    compare_values(&comparison, value).map_err(|err| err.for_key(key))
+}
+
 #[cfg(test)]
 mod test {
-    use crate::compare::check_preconditions;
+    use crate::compare::compare_distance;
     use crate::compare::compare_values;
+    use crate::domain::CompiledDomain;
+    use crate::error::PlanError;
     use crate::prelude::*;
 
+    /// Checks all the preconditions from the `Action` against passed in `LocalState` the way the
+    /// planner does, by compiling the `Action` and applying it to the `LocalState`.
+    /// Returns `true` if all the preconditions pass (or if there is none), otherwise `false`
+    ///
+    /// All preconditions are checked even if an earlier one fails, so a mistake in the
+    /// definition of the `Action` is always reported as a [`PlanError`]. The
+    /// [`Action::conditions`] are checked after the ones comparing keys, and the
+    /// [`Action::precondition_fns`] last.
+    fn check_preconditions(state: &LocalState, action: &Action) -> Result<bool, PlanError> {
+        // Actions without an effect are left out of the domain
+        let action = action.clone().with_effect(Effect::new("check"));
+        let domain = CompiledDomain::new(&[action], &Goal::new());
+        let applied = domain.successor(
+            &domain.state_from(state),
+            &domain.compiled_actions[0],
+            &mut Vec::new(),
+            &mut state.clone(),
+        )?;
+        Ok(applied.is_some())
+    }
+
     #[test]
     fn test_check_preconditions_empty() {
         let state = LocalState::default().with_datum("is_hungry", true);
         let action = Action::default();
 
-        let result = check_preconditions(&state, &action);
+        let result = check_preconditions(&state, &action).unwrap();
         assert!(result);
     }
 
@@ -114,16 +442,37 @@ mod test {
         let state = LocalState::default().with_datum("is_hungry", true);
         let action = Action::default().with_precondition(("is_hungry", Compare::equals(true)));
 
-        let result = check_preconditions(&state, &action);
+        let result = check_preconditions(&state, &action).unwrap();
         assert!(result);
     }
 
+    #[test]
+    fn test_check_preconditions_fn() {
+        let state = LocalState::default()
+            .with_datum("energy", 10_i64)
+            .with_datum("hunger", 4_i64);
+        let twice_hunger = |state: &LocalState| match (
+            state.data.get(&Key::from("energy")),
+            state.data.get(&Key::from("hunger")),
+        ) {
+            (Some(Datum::I64(energy)), Some(Datum::I64(hunger))) => *energy >= 2 * hunger,
+            _ => false,
+        };
+        let action = Action::default()
+            .with_precondition(("energy", Compare::greater_than_equals(5_i64)))
+            .with_precondition_fn("energy_twice_hunger", twice_hunger);
+        assert!(check_preconditions(&state, &action).unwrap());
+
+        let state = state.with_datum("hunger", 6_i64);
+        assert!(!check_preconditions(&state, &action).unwrap());
+    }
+
     #[test]
     fn test_check_preconditions_false() {
         let state = LocalState::default().with_datum("is_hungry", true);
         let action = Action::default().with_precondition(("is_hungry", Compare::equals(false)));
 
-        let result = check_preconditions(&state, &action);
+        let result = check_preconditions(&state, &action).unwrap();
         assert!(!result);
     }
 
@@ -136,7 +485,7 @@ mod test {
             .with_precondition(("is_hungry", Compare::equals(false)))
             .with_precondition(("is_hungry", Compare::equals(true)));
 
-        let result = check_preconditions(&state, &action);
+        let result = check_preconditions(&state, &action).unwrap();
         assert!(!result);
 
         // True + False
@@ -144,7 +493,7 @@ mod test {
             .with_precondition(("is_hungry", Compare::equals(true)))
             .with_precondition(("is_hungry", Compare::equals(false)));
 
-        let result = check_preconditions(&state, &action);
+        let result = check_preconditions(&state, &action).unwrap();
         assert!(!result);
     }
 
@@ -161,7 +510,8 @@ mod test {
             let ret = compare_values(
                 &Compare::GreaterThanEquals(Datum::I64(val1)),
                 &Datum::I64(val2),
-            );
+            )
+            .unwrap();
             assert_eq!(
                 ret, expected,
                 "Expected {val1} to be greater than or equal to {val2}, but compare_values returned {ret:#?}"
@@ -182,7 +532,8 @@ mod test {
             let ret = compare_values(
                 &Compare::LessThanEquals(Datum::I64(val1)),
                 &Datum::I64(val2),
-            );
+            )
+            .unwrap();
             assert_eq!(
                 ret, expected,
                 "Expected {val1} to be less than or equal to {val2}, but compare_values returned {ret:#?}"
@@ -200,11 +551,146 @@ mod test {
         ];
 
         for (val1, val2, expected) in cases {
-            let ret = compare_values(&Compare::NotEquals(Datum::I64(val1)), &Datum::I64(val2));
+            let ret =
+                compare_values(&Compare::NotEquals(Datum::I64(val1)), &Datum::I64(val2)).unwrap();
             assert_eq!(
                 ret, expected,
                 "Expected {val1} to not be equal to {val2}, but compare_values returned {ret:#?}"
             );
         }
     }
+
+    #[test]
+    fn test_strict_ranges_and_sets() {
+        let cases = vec![
+            (Compare::greater_than(10_i64), 10_i64, false),
+            (Compare::greater_than(10_i64), 11_i64, true),
+            (Compare::less_than(10_i64), 10_i64, false),
+            (Compare::less_than(10_i64), 9_i64, true),
+            (Compare::in_range(10_i64, 20_i64), 10_i64, true),
+            (Compare::in_range(10_i64, 20_i64), 20_i64, true),
+            (Compare::in_range(10_i64, 20_i64), 21_i64, false),
+            (Compare::one_of([1_i64, 3, 5]), 3_i64, true),
+            (Compare::one_of([1_i64, 3, 5]), 4_i64, false),
+            (Compare::one_of(Vec::<i64>::new()), 4_i64, false),
+        ];
+
+        for (comparison, value, expected) in cases {
+            let ret = compare_values(&comparison, &Datum::I64(value)).unwrap();
+            assert_eq!(
+                ret, expected,
+                "Expected {value} against {comparison:?} to be {expected}"
+            );
+        }
+
+        assert_eq!(
+            Err(PlanError::TypeMismatch {
+                key: None,
+                left: Datum::I64(4),
+                right: Datum::F64(5.0),
+            }),
+            compare_values(
+                &Compare::InRange(Datum::I64(1), Datum::F64(5.0)),
+                &Datum::I64(4)
+            )
+        );
+    }
+
+    #[test]
+    fn test_compare_to_key() {
+        let state = LocalState::default()
+            .with_datum("gold", 10_i64)
+            .with_datum("price", 12_i64);
+        let action = Action::default()
+            .with_precondition(("gold", Compare::greater_than_equals_key("price")));
+        assert!(!check_preconditions(&state, &action).unwrap());
+        let state = state.with_datum("price", 10_i64);
+        assert!(check_preconditions(&state, &action).unwrap());
+
+        // Without the state, the other key has no value to compare against
+        let comparison = Compare::equals_key("price");
+        assert_eq!(
+            Err(PlanError::MissingKey("price".into())),
+            compare_values(&comparison, &Datum::I64(10))
+        );
+        assert_eq!(
+            Ok(true),
+            compare_values(&comparison.resolve(&state).unwrap(), &Datum::I64(10))
+        );
+
+        let action = Action::default().with_precondition(("gold", Compare::less_than_key("tax")));
+        assert_eq!(
+            Err(PlanError::MissingKey("tax".into())),
+            check_preconditions(&state, &action)
+        );
+    }
+
+    #[test]
+    fn test_check_preconditions_missing_key() {
+        let state = LocalState::default().with_datum("is_hungry", true);
+        let action = Action::default()
+            .with_precondition(("is_hungry", Compare::equals(false)))
+            .with_precondition(("is_tired", Compare::equals(false)));
+
+        let result = check_preconditions(&state, &action);
+        assert_eq!(result, Err(PlanError::MissingKey("is_tired".into())));
+    }
+
+    #[test]
+    fn test_check_preconditions_type_mismatch() {
+        let state = LocalState::default().with_datum("energy", 10_i64);
+        let action =
+            Action::default().with_precondition(("energy", Compare::greater_than_equals(5.0)));
+
+        let result = check_preconditions(&state, &action);
+        assert_eq!(
+            result,
+            Err(PlanError::TypeMismatch {
+                key: Some("energy".into()),
+                left: Datum::I64(10),
+                right: Datum::F64(5.0),
+            })
+        );
+    }
+
+    #[test]
+    fn test_compare_distance() {
+        let cases = vec![
+            (Compare::equals(10_i64), 4_i64, 6.0),
+            (Compare::equals(10_i64), 16_i64, 6.0),
+            (Compare::not_equals(10_i64), 10_i64, 1.0),
+            (Compare::not_equals(10_i64), 4_i64, 0.0),
+            // Already satisfied, so no matter how far from the value it is
+            (Compare::greater_than_equals(10_i64), 50_i64, 0.0),
+            (Compare::greater_than_equals(10_i64), 4_i64, 6.0),
+            (Compare::less_than_equals(10_i64), 4_i64, 0.0),
+            (Compare::less_than_equals(10_i64), 16_i64, 6.0),
+            // Strict bounds need one more
+            (Compare::greater_than(10_i64), 10_i64, 1.0),
+            (Compare::greater_than(10_i64), 4_i64, 7.0),
+            (Compare::less_than(10_i64), 16_i64, 7.0),
+            (Compare::in_range(10_i64, 20_i64), 15_i64, 0.0),
+            (Compare::in_range(10_i64, 20_i64), 4_i64, 6.0),
+            (Compare::in_range(10_i64, 20_i64), 23_i64, 3.0),
+            (Compare::one_of([10_i64, 20_i64]), 18_i64, 2.0),
+            (Compare::one_of([10_i64, 20_i64]), 10_i64, 0.0),
+            (Compare::one_of(Vec::<i64>::new()), 10_i64, 1.0),
+        ];
+
+        for (comparison, value, expected) in cases {
+            let distance = compare_distance(&comparison, &Datum::I64(value)).unwrap();
+            assert_eq!(
+                distance, expected,
+                "Expected {value} to be {expected} away from {comparison:?}"
+            );
+        }
+
+        // Floats aren't rounded to whole numbers
+        let distance = compare_distance(&Compare::equals(1.0), &Datum::F64(0.5)).unwrap();
+        assert_eq!(distance, 0.5);
+        let distance = compare_distance(&Compare::greater_than(1.0), &Datum::F64(1.0)).unwrap();
+        assert!(distance > 0.0 && distance < 1e-300);
+        let distance = compare_distance(&Compare::equals(true), &Datum::Bool(false)).unwrap();
+        assert_eq!(distance, 1.0);
+    }
 }
@@ -0,0 +1,181 @@
+use std::ops::Not;
+
+use crate::{
+    compare::{Compare, compare_key},
+    error::PlanError,
+    key::Key,
+    localstate::LocalState,
+};
+
+/// Combines comparisons of keys with `And`, `Or` and `Not`, for preconditions and requirements
+/// that can't be written as one [`Compare`] per key that all have to hold, like having an axe or
+/// a pickaxe. Add it to an [`Action`](crate::action::Action) with
+/// [`Action::with_condition`](crate::action::Action::with_condition), and to a
+/// [`Goal`](crate::goal::Goal) with [`Goal::with_condition`](crate::goal::Goal::with_condition).
+///
+/// ```
+/// # use dogoap::prelude::*;
+/// let can_mine = Condition::any([
+///     ("has_axe", Compare::equals(true)),
+///     ("has_pickaxe", Compare::equals(true)),
+/// ]);
+/// let state = LocalState::new()
+///     .with_datum("has_axe", false)
+///     .with_datum("has_pickaxe", true);
+/// assert_eq!(Ok(true), can_mine.check(&state));
+/// assert_eq!(Ok(false), (!can_mine).check(&state));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(opaque, Clone, Debug, PartialEq, Hash)
+)]
+pub enum Condition {
+    /// Holds when the value of the key passes the [`Compare`]
+    Compare(Key, Compare),
+    /// Holds when all of the conditions hold, or when there are none
+    And(Vec<Condition>),
+    /// Holds when any of the conditions holds, but never when there are none
+    Or(Vec<Condition>),
+    /// Holds when the condition doesn't
+    Not(Box<Condition>),
+}
+
+impl Condition {
+    /// Convenience method for creating a [`Condition::Compare`]
+    pub fn compare(key: impl Into<Key>, compare: Compare) -> Self {
+        Condition::Compare(key.into(), compare)
+    }
+
+    /// Convenience method for creating a [`Condition::And`]
+    pub fn all(conditions: impl IntoIterator<Item = impl Into<Condition>>) -> Self {
+        Condition::And(conditions.into_iter().map(Into::into).collect())
+    }
+
+    /// Convenience method for creating a [`Condition::Or`]
+    pub fn any(conditions: impl IntoIterator<Item = impl Into<Condition>>) -> Self {
+        Condition::Or(conditions.into_iter().map(Into::into).collect())
+    }
+
+    /// Checks the condition against `state`
+    ///
+    /// Every comparison is checked, even when the outcome is already known, so a mistake like a
+    /// missing key is always reported as a [`PlanError`].
+    pub fn check(&self, state: &LocalState) -> Result<bool, PlanError> {
+        self.check_with(&mut |key, compare| compare_key(state, key, compare))
+    }
+
+    /// Checks the condition with `compare` checking each of its comparisons
+    pub(crate) fn check_with(
+        &self,
+        compare: &mut impl FnMut(Key, &Compare) -> Result<bool, PlanError>,
+    ) -> Result<bool, PlanError> {
+        match self {
+            Condition::Compare(key, comparison) => compare(*key, comparison),
+            Condition::And(conditions) => conditions.iter().try_fold(true, |passed, condition| {
+                Ok(condition.check_with(compare)? && passed)
+            }),
+            Condition::Or(conditions) => conditions.iter().try_fold(false, |passed, condition| {
+                Ok(condition.check_with(compare)? || passed)
+            }),
+            Condition::Not(condition) => Ok(!condition.check_with(compare)?),
+        }
+    }
+
+    /// Calls `visit` with every key the condition compares, or compares against
+    pub(crate) fn visit_keys(&self, visit: &mut impl FnMut(Key)) {
+        match self {
+            Condition::Compare(key, compare) => {
+                visit(*key);
+                if let Some(other) = compare.other_key() {
+                    visit(other);
+                }
+            }
+            Condition::And(conditions) | Condition::Or(conditions) => {
+                for condition in conditions {
+                    condition.visit_keys(visit);
+                }
+            }
+            Condition::Not(condition) => condition.visit_keys(visit),
+        }
+    }
+
+    /// Folds the condition into a value, after pushing every `Not` down to the comparisons.
+    /// `leaf` gets each comparison, where a negated one is replaced by the comparisons that hold
+    /// exactly when it doesn't. `and` and `or` combine the values of the conditions below them,
+    /// which can be none at all.
+    pub(crate) fn fold<T>(
+        &self,
+        leaf: &mut impl FnMut(Key, &Compare) -> T,
+        and: &mut impl FnMut(Vec<T>) -> T,
+        or: &mut impl FnMut(Vec<T>) -> T,
+    ) -> T {
+        self.fold_negated(false, leaf, and, or)
+    }
+
+    fn fold_negated<T>(
+        &self,
+        negated: bool,
+        leaf: &mut impl FnMut(Key, &Compare) -> T,
+        and: &mut impl FnMut(Vec<T>) -> T,
+        or: &mut impl FnMut(Vec<T>) -> T,
+    ) -> T {
+        match self {
+            Condition::Compare(key, compare) if negated => {
+                negate(*key, compare).fold_negated(false, leaf, and, or)
+            }
+            Condition::Compare(key, compare) => leaf(*key, compare),
+            Condition::And(conditions) | Condition::Or(conditions) => {
+                let values = conditions
+                    .iter()
+                    .map(|condition| condition.fold_negated(negated, leaf, and, or))
+                    .collect();
+                // Not (a and b) is (not a) or (not b), and the other way around
+                if matches!(self, Condition::And(_)) != negated {
+                    and(values)
+                } else {
+                    or(values)
+                }
+            }
+            Condition::Not(condition) => condition.fold_negated(!negated, leaf, and, or),
+        }
+    }
+}
+
+/// A condition without `Not` that holds exactly when `compare` doesn't hold for `key`
+fn negate(key: Key, compare: &Compare) -> Condition {
+    let compare = match compare {
+        Compare::Equals(value) => Compare::NotEquals(*value),
+        Compare::NotEquals(value) => Compare::Equals(*value),
+        Compare::GreaterThanEquals(value) => Compare::LessThan(*value),
+        Compare::LessThanEquals(value) => Compare::GreaterThan(*value),
+        Compare::GreaterThan(value) => Compare::LessThanEquals(*value),
+        Compare::LessThan(value) => Compare::GreaterThanEquals(*value),
+        Compare::ToKey(relation, other) => Compare::ToKey(relation.negate(), *other),
+        Compare::InRange(min, max) => {
+            return Condition::any([
+                (key, Compare::LessThan(*min)),
+                (key, Compare::GreaterThan(*max)),
+            ]);
+        }
+        Compare::OneOf(values) => {
+            return Condition::all(values.iter().map(|value| (key, Compare::NotEquals(*value))));
+        }
+    };
+    Condition::Compare(key, compare)
+}
+
+impl<K: Into<Key>> From<(K, Compare)> for Condition {
+    fn from((key, compare): (K, Compare)) -> Self {
+        Condition::Compare(key.into(), compare)
+    }
+}
+
+impl Not for Condition {
+    type Output = Condition;
+
+    fn not(self) -> Condition {
+        Condition::Not(Box::new(self))
+    }
+}
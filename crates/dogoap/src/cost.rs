@@ -0,0 +1,208 @@
+use std::cmp::Ordering;
+use std::fmt::{self, Debug, Display};
+use std::hash::{Hash, Hasher};
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Mul, Sub};
+use std::sync::Arc;
+
+use crate::localstate::LocalState;
+
+/// The cost of an [`Action`](crate::action::Action), or of a whole plan
+///
+/// A `f64`, so costs can be fractional, like distances or durations, that can be ordered and
+/// hashed like the planner needs. Anything that takes a cost accepts integers and floats as well.
+/// Costs are never NaN, and shouldn't be negative. A NaN, like from a [`CostFn`] or from
+/// `Cost::INFINITY - Cost::INFINITY`, becomes [`Cost::INFINITY`] instead of taking down the
+/// search.
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(opaque, Clone, Debug, PartialEq, Hash)
+)]
+pub struct Cost(f64);
+
+impl Cost {
+    /// Nothing to pay
+    pub const ZERO: Cost = Cost(0.0);
+    /// What every [`Effect`](crate::effect::Effect) costs unless told otherwise
+    pub const ONE: Cost = Cost(1.0);
+    /// The cost of what can't be done at all
+    pub const INFINITY: Cost = Cost(f64::INFINITY);
+
+    /// Creates a new [`Cost`], which is [`Cost::INFINITY`] if `value` is NaN
+    pub const fn new(value: f64) -> Self {
+        if value.is_nan() {
+            return Cost::INFINITY;
+        }
+        // Adding 0 turns -0 into 0, so both compare and hash the same
+        Self(value + 0.0)
+    }
+
+    /// The cost as a `f64`
+    pub const fn value(self) -> f64 {
+        self.0
+    }
+
+    /// Whether the cost is less than [`Cost::INFINITY`]
+    pub const fn is_finite(self) -> bool {
+        self.0.is_finite()
+    }
+}
+
+impl PartialEq for Cost {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Cost {}
+
+impl PartialOrd for Cost {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Cost {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl Hash for Cost {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state);
+    }
+}
+
+impl Debug for Cost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self.0, f)
+    }
+}
+
+impl Display for Cost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl From<f64> for Cost {
+    fn from(value: f64) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<f32> for Cost {
+    fn from(value: f32) -> Self {
+        Self::new(value.into())
+    }
+}
+
+impl From<i32> for Cost {
+    fn from(value: i32) -> Self {
+        Self::new(value.into())
+    }
+}
+
+impl From<u32> for Cost {
+    fn from(value: u32) -> Self {
+        Self::new(value.into())
+    }
+}
+
+impl From<usize> for Cost {
+    fn from(value: usize) -> Self {
+        Self::new(value as f64)
+    }
+}
+
+impl Add for Cost {
+    type Output = Cost;
+
+    fn add(self, other: Cost) -> Cost {
+        Cost::new(self.0 + other.0)
+    }
+}
+
+impl AddAssign for Cost {
+    fn add_assign(&mut self, other: Cost) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Cost {
+    type Output = Cost;
+
+    fn sub(self, other: Cost) -> Cost {
+        Cost::new(self.0 - other.0)
+    }
+}
+
+/// Scales the cost, like when an [`Action`](crate::action::Action) is needed several times
+impl Mul<f64> for Cost {
+    type Output = Cost;
+
+    fn mul(self, factor: f64) -> Cost {
+        // Not doing something that can't be done costs nothing
+        if factor == 0.0 {
+            return Cost::ZERO;
+        }
+        Cost::new(self.0 * factor)
+    }
+}
+
+impl Sum for Cost {
+    fn sum<I: Iterator<Item = Cost>>(iter: I) -> Cost {
+        iter.fold(Cost::ZERO, Add::add)
+    }
+}
+
+impl<'a> Sum<&'a Cost> for Cost {
+    fn sum<I: Iterator<Item = &'a Cost>>(iter: I) -> Cost {
+        iter.copied().sum()
+    }
+}
+
+/// Works out the cost of an [`Effect`](crate::effect::Effect) from the state it's applied to, like
+/// walking somewhere costing more the farther away it is. Set it with
+/// [`Action::set_cost_fn`](crate::action::Action::set_cost_fn).
+///
+/// Returning NaN is the same as returning [`Cost::INFINITY`].
+///
+/// Two [`CostFn`]s are only equal when they are clones of each other.
+#[derive(Clone)]
+pub struct CostFn(Arc<dyn Fn(&LocalState) -> Cost + Send + Sync>);
+
+impl CostFn {
+    /// Creates a new [`CostFn`] from a closure
+    pub fn new<C: Into<Cost>>(cost_fn: impl Fn(&LocalState) -> C + Send + Sync + 'static) -> Self {
+        Self(Arc::new(move |state| cost_fn(state).into()))
+    }
+
+    /// The cost in `state`
+    pub fn cost(&self, state: &LocalState) -> Cost {
+        (self.0)(state)
+    }
+}
+
+impl PartialEq for CostFn {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for CostFn {}
+
+impl Hash for CostFn {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.0).cast::<()>().hash(state);
+    }
+}
+
+impl Debug for CostFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CostFn(..)")
+    }
+}
@@ -2,6 +2,8 @@ use std::fmt::Display;
 use std::hash::Hash;
 use std::ops::{Add, AddAssign, Sub, SubAssign};
 
+use crate::error::PlanError;
+
 /// Represents one value of either `bool`, `i64`, `f64` or a `Enum` as `usize`.
 #[derive(Clone, Debug, PartialOrd, Copy)]
 #[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
@@ -69,28 +71,141 @@ impl Eq for Datum {}
 impl Datum {
     /// Calculates the difference between two [`Datum`] values.
     ///
-    /// Panics if the two values are of different types.
+    /// Panics if the two values are of different types, see [`Datum::try_distance`]
+    /// for a version that returns an error instead.
     pub fn distance(&self, other: &Datum) -> u64 {
+        self.try_distance(other)
+            .unwrap_or_else(|err| panic!("Cannot calculate distance: {err}"))
+    }
+
+    /// Calculates the difference between two [`Datum`] values.
+    ///
+    /// Returns [`PlanError::TypeMismatch`] if the two values are of different types.
+    pub fn try_distance(&self, other: &Datum) -> Result<u64, PlanError> {
         match (self, other) {
             (Datum::Bool(a), Datum::Bool(b)) => {
                 if a == b {
-                    0
+                    Ok(0)
                 } else {
-                    1
+                    Ok(1)
                 }
             }
-            (Datum::I64(a), Datum::I64(b)) => (a - b).unsigned_abs(),
-            (Datum::F64(a), Datum::F64(b)) => (a - b).abs() as u64,
+            (Datum::I64(a), Datum::I64(b)) => Ok(a.abs_diff(*b)),
+            (Datum::F64(a), Datum::F64(b)) => Ok((a - b).abs() as u64),
             (Datum::Enum(a), Datum::Enum(b)) => {
                 if a == b {
-                    0
+                    Ok(0)
                 } else {
-                    1
+                    Ok(1)
                 }
             }
-            _ => panic!("Cannot calculate distance between different Datum types"),
+            _ => Err(self.mismatch(other)),
+        }
+    }
+
+    /// Adds two [`Datum`] values together.
+    ///
+    /// Returns [`PlanError::TypeMismatch`] unless both are either [`Datum::I64`] or [`Datum::F64`],
+    /// and [`PlanError::Overflow`] if the result doesn't fit in a [`Datum::I64`].
+    pub fn try_add(self, other: Datum) -> Result<Datum, PlanError> {
+        match (self, other) {
+            (Datum::I64(a), Datum::I64(b)) => checked(a.checked_add(b)),
+            (Datum::F64(a), Datum::F64(b)) => Ok(Datum::F64(a + b)),
+            _ => Err(self.mismatch(&other)),
+        }
+    }
+
+    /// Subtracts `other` from this [`Datum`].
+    ///
+    /// Returns [`PlanError::TypeMismatch`] unless both are either [`Datum::I64`] or [`Datum::F64`],
+    /// and [`PlanError::Overflow`] if the result doesn't fit in a [`Datum::I64`].
+    pub fn try_sub(self, other: Datum) -> Result<Datum, PlanError> {
+        match (self, other) {
+            (Datum::I64(a), Datum::I64(b)) => checked(a.checked_sub(b)),
+            (Datum::F64(a), Datum::F64(b)) => Ok(Datum::F64(a - b)),
+            _ => Err(self.mismatch(&other)),
+        }
+    }
+
+    /// Multiplies two [`Datum`] values together.
+    ///
+    /// Returns [`PlanError::TypeMismatch`] unless both are either [`Datum::I64`] or [`Datum::F64`],
+    /// and [`PlanError::Overflow`] if the result doesn't fit in a [`Datum::I64`].
+    pub fn try_mul(self, other: Datum) -> Result<Datum, PlanError> {
+        match (self, other) {
+            (Datum::I64(a), Datum::I64(b)) => checked(a.checked_mul(b)),
+            (Datum::F64(a), Datum::F64(b)) => Ok(Datum::F64(a * b)),
+            _ => Err(self.mismatch(&other)),
+        }
+    }
+
+    /// Divides this [`Datum`] by `other`, rounding a [`Datum::I64`] towards zero.
+    ///
+    /// Returns [`PlanError::TypeMismatch`] unless both are either [`Datum::I64`] or [`Datum::F64`],
+    /// [`PlanError::DivisionByZero`] if `other` is zero, and [`PlanError::Overflow`] if the
+    /// result doesn't fit in a [`Datum::I64`], like for `i64::MIN / -1`.
+    pub fn try_div(self, other: Datum) -> Result<Datum, PlanError> {
+        match (self, other) {
+            (Datum::I64(_), Datum::I64(0)) | (Datum::F64(_), Datum::F64(0.0)) => {
+                Err(PlanError::DivisionByZero { key: None })
+            }
+            (Datum::I64(a), Datum::I64(b)) => checked(a.checked_div(b)),
+            (Datum::F64(a), Datum::F64(b)) => Ok(Datum::F64(a / b)),
+            _ => Err(self.mismatch(&other)),
+        }
+    }
+
+    /// The smaller of two [`Datum`] values.
+    ///
+    /// Returns [`PlanError::TypeMismatch`] unless both are either [`Datum::I64`] or [`Datum::F64`].
+    pub fn try_min(self, other: Datum) -> Result<Datum, PlanError> {
+        match (self, other) {
+            (Datum::I64(a), Datum::I64(b)) => Ok(Datum::I64(a.min(b))),
+            (Datum::F64(a), Datum::F64(b)) => Ok(Datum::F64(a.min(b))),
+            _ => Err(self.mismatch(&other)),
+        }
+    }
+
+    /// The larger of two [`Datum`] values.
+    ///
+    /// Returns [`PlanError::TypeMismatch`] unless both are either [`Datum::I64`] or [`Datum::F64`].
+    pub fn try_max(self, other: Datum) -> Result<Datum, PlanError> {
+        match (self, other) {
+            (Datum::I64(a), Datum::I64(b)) => Ok(Datum::I64(a.max(b))),
+            (Datum::F64(a), Datum::F64(b)) => Ok(Datum::F64(a.max(b))),
+            _ => Err(self.mismatch(&other)),
         }
     }
+
+    /// Flips a [`Datum::Bool`].
+    ///
+    /// Returns [`PlanError::TypeMismatch`] for anything but a [`Datum::Bool`].
+    pub fn try_toggle(self) -> Result<Datum, PlanError> {
+        match self {
+            Datum::Bool(b) => Ok(Datum::Bool(!b)),
+            _ => Err(self.mismatch(&Datum::Bool(false))),
+        }
+    }
+
+    /// Whether both values are the same variant, like both being [`Datum::Bool`]
+    pub fn same_type(&self, other: &Datum) -> bool {
+        core::mem::discriminant(self) == core::mem::discriminant(other)
+    }
+
+    pub(crate) fn mismatch(&self, other: &Datum) -> PlanError {
+        PlanError::TypeMismatch {
+            key: None,
+            left: *self,
+            right: *other,
+        }
+    }
+}
+
+/// Wraps the result of checked [`Datum::I64`] math, which is `None` if it overflowed
+fn checked(result: Option<i64>) -> Result<Datum, PlanError> {
+    result
+        .map(Datum::I64)
+        .ok_or(PlanError::Overflow { key: None })
 }
 
 impl Display for Datum {
@@ -116,11 +231,8 @@ impl Add for Datum {
     type Output = Datum;
 
     fn add(self, other: Datum) -> Datum {
-        match (self, other) {
-            (Datum::I64(a), Datum::I64(b)) => Datum::I64(a + b),
-            (Datum::F64(a), Datum::F64(b)) => Datum::F64(a + b),
-            _ => panic!("Unsupported addition between Datum variants, {self:?} - {other:?}"),
-        }
+        self.try_add(other)
+            .unwrap_or_else(|err| panic!("Unsupported addition between Datum variants: {err}"))
     }
 }
 
@@ -128,51 +240,20 @@ impl Sub for Datum {
     type Output = Datum;
 
     fn sub(self, other: Datum) -> Datum {
-        match (self, other) {
-            (Datum::I64(a), Datum::I64(b)) => Datum::I64(a - b),
-            (Datum::F64(a), Datum::F64(b)) => Datum::F64(a - b),
-            _ => panic!("Unsupported negation between Datum variants, {self:?} - {other:?}"),
-        }
+        self.try_sub(other)
+            .unwrap_or_else(|err| panic!("Unsupported negation between Datum variants: {err}"))
     }
 }
 
 impl AddAssign for Datum {
     fn add_assign(&mut self, rhs: Self) {
-        match self {
-            Self::I64(v1) => match rhs {
-                Self::I64(v2) => {
-                    *v1 += v2;
-                }
-                _ => unimplemented!("Unimplemented! Tried to remove {self:?} from {rhs:?}"),
-            },
-            Self::F64(v1) => match rhs {
-                Self::F64(v2) => {
-                    *v1 += v2;
-                }
-                _ => unimplemented!("Unimplemented! Tried to remove {self:?} from {rhs:?}"),
-            },
-            _ => unimplemented!("Unimplemented! Tried to remove {self:?} from {rhs:?}"),
-        }
+        *self = *self + rhs;
     }
 }
 
 impl SubAssign for Datum {
     fn sub_assign(&mut self, rhs: Self) {
-        match self {
-            Self::I64(v1) => match rhs {
-                Self::I64(v2) => {
-                    *v1 -= v2;
-                }
-                _ => unimplemented!("Unimplemented! Tried to remove {self:?} from {rhs:?}"),
-            },
-            Self::F64(v1) => match rhs {
-                Self::F64(v2) => {
-                    *v1 -= v2;
-                }
-                _ => unimplemented!("Unimplemented! Tried to remove {self:?} from {rhs:?}"),
-            },
-            _ => unimplemented!("Unimplemented! Tried to remove {self:?} from {rhs:?}"),
-        }
+        *self = *self - rhs;
     }
 }
 
@@ -242,4 +323,61 @@ mod test {
         assert_eq!(Datum::Enum(1).distance(&Datum::Enum(0)), 1);
         assert_eq!(Datum::Enum(1).distance(&Datum::Enum(5)), 1);
     }
+
+    #[test]
+    fn test_mismatched_types() {
+        let mismatch = Err(PlanError::TypeMismatch {
+            key: None,
+            left: Datum::I64(1),
+            right: Datum::Bool(true),
+        });
+        assert_eq!(Datum::I64(1).try_add(Datum::Bool(true)), mismatch);
+        assert_eq!(Datum::I64(1).try_sub(Datum::Bool(true)), mismatch);
+        assert!(Datum::I64(1).try_distance(&Datum::Bool(true)).is_err());
+        assert!(Datum::Bool(true).try_add(Datum::Bool(true)).is_err());
+
+        assert_eq!(
+            Datum::F64(1.5).try_add(Datum::F64(1.0)),
+            Ok(Datum::F64(2.5))
+        );
+        assert_eq!(Datum::I64(1).try_sub(Datum::I64(3)), Ok(Datum::I64(-2)));
+    }
+
+    #[test]
+    fn test_scaling_and_clamping() {
+        assert_eq!(Datum::I64(7).try_mul(Datum::I64(3)), Ok(Datum::I64(21)));
+        assert_eq!(Datum::I64(-7).try_div(Datum::I64(2)), Ok(Datum::I64(-3)));
+        assert_eq!(
+            Datum::F64(1.0).try_div(Datum::F64(4.0)),
+            Ok(Datum::F64(0.25))
+        );
+        let division_by_zero = Err(PlanError::DivisionByZero { key: None });
+        assert_eq!(Datum::I64(1).try_div(Datum::I64(0)), division_by_zero);
+        assert_eq!(Datum::F64(1.0).try_div(Datum::F64(-0.0)), division_by_zero);
+
+        assert_eq!(Datum::I64(7).try_min(Datum::I64(5)), Ok(Datum::I64(5)));
+        assert_eq!(Datum::I64(3).try_min(Datum::I64(5)), Ok(Datum::I64(3)));
+        assert_eq!(
+            Datum::F64(2.0).try_max(Datum::F64(2.5)),
+            Ok(Datum::F64(2.5))
+        );
+        assert_eq!(Datum::Bool(false).try_toggle(), Ok(Datum::Bool(true)));
+
+        assert!(Datum::Bool(true).try_mul(Datum::Bool(true)).is_err());
+        assert!(Datum::I64(1).try_max(Datum::F64(1.0)).is_err());
+        assert!(Datum::I64(1).try_toggle().is_err());
+    }
+
+    #[test]
+    fn test_overflow() {
+        let overflow = Err(PlanError::Overflow { key: None });
+        assert_eq!(Datum::I64(i64::MAX).try_add(Datum::I64(1)), overflow);
+        assert_eq!(Datum::I64(i64::MIN).try_sub(Datum::I64(1)), overflow);
+        assert_eq!(Datum::I64(i64::MAX).try_mul(Datum::I64(2)), overflow);
+        assert_eq!(Datum::I64(i64::MIN).try_div(Datum::I64(-1)), overflow);
+        assert_eq!(
+            Datum::I64(i64::MIN).try_distance(&Datum::I64(i64::MAX)),
+            Ok(u64::MAX)
+        );
+    }
 }
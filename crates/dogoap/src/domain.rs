@@ -0,0 +1,655 @@
+//! Compiling [`Action`]s and a [`Goal`] into a form that is cheap to search through
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+
+use rustc_hash::FxHasher;
+
+use crate::{
+    action::Action,
+    alternatives,
+    compare::{Compare, compare_values},
+    condition::Condition,
+    cost::Cost,
+    datum::Datum,
+    effect::Effect,
+    error::PlanError,
+    goal::Goal,
+    heuristic::{DistanceHeuristic, Heuristic, HeuristicContext},
+    key::Key,
+    localstate::LocalState,
+    mutator::{Mutator, apply_mutator_to_value},
+    planner::{Plan, PlanOptions, PlanningStrategy, replay_plan},
+    regression::{RegressionNode, can_regress, make_plan_regressive},
+    repair,
+    search::{SearchBuffers, SearchSpace, Successor, search},
+};
+
+/// The memory the planner works in
+///
+/// Every plan needs room for the states it has seen, the ones it still has to look at and so on.
+/// Instead of allocating all of that again for every plan, keep a [`PlanningContext`] around
+/// and plan with [`CompiledDomain::make_plan_with_context`]. A context can be used with any
+/// [`CompiledDomain`], but only for one plan at a time, so keep one per thread. It holds on to
+/// as much memory as the biggest plan it was used for needed.
+#[derive(Default)]
+pub struct PlanningContext {
+    pub(crate) forward: SearchBuffers<State>,
+    regression: SearchBuffers<RegressionNode>,
+    /// Values of states from earlier plans, to be reused for new states
+    pub(crate) free_values: Vec<Box<[Option<Datum>]>>,
+}
+
+impl PlanningContext {
+    /// Creates a new, empty [`PlanningContext`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes the values of earlier states to reuse for states of `domain`. Hand them back to
+    /// [`Self::free_values`] once the search is done.
+    pub(crate) fn take_free_values(
+        &mut self,
+        domain: &CompiledDomain,
+    ) -> Vec<Box<[Option<Datum>]>> {
+        // The context might have been used with a domain that has a different number of slots
+        // last time
+        if self
+            .free_values
+            .first()
+            .is_some_and(|values| values.len() != domain.keys.len())
+        {
+            self.free_values.clear();
+        }
+        std::mem::take(&mut self.free_values)
+    }
+}
+
+impl std::fmt::Debug for PlanningContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PlanningContext").finish_non_exhaustive()
+    }
+}
+
+/// The value of every slot of a [`CompiledDomain`], `None` where the key is missing
+///
+/// Keeps a Zobrist hash of its values: every slot and value pair hashes to a random-looking
+/// number, and the hash of the state is all of them combined with XOR. Changing a value only
+/// needs to XOR out the old pair and XOR in the new one, so the search never has to walk all the
+/// values to hash a state, and states that hash differently are known to differ right away.
+#[derive(Clone, Debug)]
+pub(crate) struct State {
+    hash: u64,
+    pub(crate) values: Box<[Option<Datum>]>,
+}
+
+impl State {
+    fn new(values: Box<[Option<Datum>]>) -> Self {
+        let hash = values
+            .iter()
+            .enumerate()
+            .fold(0, |hash, (slot, value)| hash ^ zobrist(slot, value));
+        Self { hash, values }
+    }
+
+    /// Clones the state into one of the `free` buffers, if there is one
+    fn clone_reusing(&self, free: &mut Vec<Box<[Option<Datum>]>>) -> Self {
+        let values = match free.pop() {
+            Some(mut values) => {
+                values.copy_from_slice(&self.values);
+                values
+            }
+            None => self.values.clone(),
+        };
+        Self {
+            hash: self.hash,
+            values,
+        }
+    }
+
+    fn set(&mut self, slot: usize, value: Option<Datum>) {
+        self.hash ^= zobrist(slot, &self.values[slot]) ^ zobrist(slot, &value);
+        self.values[slot] = value;
+    }
+}
+
+impl PartialEq for State {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash && self.values == other.values
+    }
+}
+
+impl Eq for State {}
+
+impl Hash for State {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash);
+    }
+}
+
+/// The Zobrist key of `value` in `slot`, see [`State`]
+fn zobrist(slot: usize, value: &Option<Datum>) -> u64 {
+    let mut hasher = FxHasher::default();
+    (slot, value).hash(&mut hasher);
+    // FxHasher barely mixes the last value it's given, but for XORing the hashes of different
+    // slots together every bit needs to depend on all of the input. This is the finalizer of
+    // SplitMix64.
+    let mut hash = hasher.finish();
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^ (hash >> 31)
+}
+
+/// A set of [`Action`]s and a [`Goal`], prepared for planning
+///
+/// Every key used by a precondition, mutator or requirement gets a slot, and the planner works
+/// on a fixed-length list of values instead of a [`LocalState`], which makes the states it looks
+/// at cheap to create, clone and hash. Keys that only appear in the start state have no slot, as
+/// nothing can check or change them.
+///
+/// [`make_plan`](crate::prelude::make_plan) compiles its arguments on every call. When you plan
+/// for the same [`Action`]s and [`Goal`] over and over, compile them once and use
+/// [`CompiledDomain::make_plan`] instead.
+#[derive(Clone, Debug)]
+pub struct CompiledDomain {
+    actions: Vec<Action>,
+    goal: Goal,
+    /// The key of each slot
+    keys: Vec<Key>,
+    slots: BTreeMap<Key, usize>,
+    pub(crate) compiled_actions: Vec<CompiledAction>,
+    requirements: Vec<(usize, Compare)>,
+    /// For each slot, whether a [`Mutator::Remove`] can remove its key
+    removable: Vec<bool>,
+}
+
+/// An [`Action`] with its keys replaced by slots
+#[derive(Clone, Debug)]
+pub(crate) struct CompiledAction {
+    /// Index into [`CompiledDomain::actions`]
+    pub(crate) action: usize,
+    preconditions: Vec<(usize, Compare)>,
+    mutators: Vec<(usize, Mutator)>,
+}
+
+impl CompiledDomain {
+    /// Compiles `actions` and `goal`
+    pub fn new(actions: &[Action], goal: &Goal) -> Self {
+        let mut domain = Self {
+            actions: actions.to_vec(),
+            goal: goal.clone(),
+            keys: Vec::new(),
+            slots: BTreeMap::new(),
+            compiled_actions: Vec::new(),
+            requirements: Vec::new(),
+            removable: Vec::new(),
+        };
+        domain.requirements = goal
+            .requirements
+            .iter()
+            .map(|(key, compare)| {
+                (
+                    domain.compare_slot_or_insert(*key, compare),
+                    compare.clone(),
+                )
+            })
+            .collect();
+        let conditions = goal
+            .conditions
+            .iter()
+            .chain(actions.iter().flat_map(|action| &action.conditions));
+        for condition in conditions {
+            condition.visit_keys(&mut |key| {
+                domain.slot_or_insert(key);
+            });
+        }
+        for (index, action) in actions.iter().enumerate() {
+            // Actions without effects can't be part of a plan
+            let Some(effect) = action.effects.first() else {
+                continue;
+            };
+            let compiled = CompiledAction {
+                action: index,
+                preconditions: action
+                    .preconditions
+                    .iter()
+                    .map(|(key, compare)| {
+                        (
+                            domain.compare_slot_or_insert(*key, compare),
+                            compare.clone(),
+                        )
+                    })
+                    .collect(),
+                mutators: effect
+                    .mutators
+                    .iter()
+                    .map(|mutator| (domain.mutator_slot_or_insert(mutator), mutator.clone()))
+                    .collect(),
+            };
+            domain.compiled_actions.push(compiled);
+        }
+        domain.removable = vec![false; domain.keys.len()];
+        for (slot, mutator) in domain
+            .compiled_actions
+            .iter()
+            .flat_map(|action| &action.mutators)
+        {
+            if let Mutator::Remove(_) = mutator {
+                domain.removable[*slot] = true;
+            }
+        }
+        domain
+    }
+
+    /// Changes the cost of the [`Action`] at `index` in [`CompiledDomain::actions`]
+    pub(crate) fn set_action_cost(&mut self, index: usize, cost: Cost) {
+        if let Some(effect) = self.actions[index].effects.first_mut() {
+            effect.cost = cost;
+        }
+    }
+
+    /// The cost of the [`Action`] at `index` in [`CompiledDomain::actions`] when applied to
+    /// `state`. `scratch` is the start state, which the slots are written into when the cost
+    /// depends on the state.
+    pub(crate) fn action_cost(
+        &self,
+        index: usize,
+        state: &State,
+        scratch: &mut LocalState,
+    ) -> Cost {
+        let effect = &self.actions[index].effects[0];
+        match &effect.cost_fn {
+            Some(cost_fn) => {
+                self.write_slots(&state.values, scratch);
+                cost_fn.cost(scratch)
+            }
+            None => effect.cost,
+        }
+    }
+
+    fn slot_or_insert(&mut self, key: Key) -> usize {
+        *self.slots.entry(key).or_insert_with(|| {
+            self.keys.push(key);
+            self.keys.len() - 1
+        })
+    }
+
+    /// The slot of `key`, making sure the key `compare` compares against has one too
+    fn compare_slot_or_insert(&mut self, key: Key, compare: &Compare) -> usize {
+        if let Some(other) = compare.other_key() {
+            self.slot_or_insert(other);
+        }
+        self.slot_or_insert(key)
+    }
+
+    /// The slot of the key `mutator` changes, making sure the key it reads from has one too
+    fn mutator_slot_or_insert(&mut self, mutator: &Mutator) -> usize {
+        if let Some(other) = mutator.other_key() {
+            self.slot_or_insert(other);
+        }
+        self.slot_or_insert(mutator.key())
+    }
+
+    /// The [`Action`]s this domain was compiled from
+    pub fn actions(&self) -> &[Action] {
+        &self.actions
+    }
+
+    /// The [`Goal`] this domain was compiled from
+    pub fn goal(&self) -> &Goal {
+        &self.goal
+    }
+
+    /// The keys that got a slot, in slot order
+    pub fn keys(&self) -> &[Key] {
+        &self.keys
+    }
+
+    /// The slot of `key`, if any precondition, mutator or requirement uses it
+    pub fn slot(&self, key: impl Into<Key>) -> Option<usize> {
+        self.slots.get(&key.into()).copied()
+    }
+
+    /// Like [`make_plan_with_options`](crate::planner::make_plan_with_options), but for the
+    /// [`Action`]s and [`Goal`] of this domain
+    pub fn make_plan(&self, start: &LocalState, options: &PlanOptions) -> Result<Plan, PlanError> {
+        self.make_plan_with_context(start, options, &mut PlanningContext::new())
+    }
+
+    /// Like [`CompiledDomain::make_plan`], but reuses the memory in `context` instead of
+    /// allocating it again
+    pub fn make_plan_with_context(
+        &self,
+        start: &LocalState,
+        options: &PlanOptions,
+        context: &mut PlanningContext,
+    ) -> Result<Plan, PlanError> {
+        self.plan(start, options, false, context)
+    }
+
+    /// Like [`make_plan_anytime`](crate::planner::make_plan_anytime), but for the [`Action`]s
+    /// and [`Goal`] of this domain
+    pub fn make_plan_anytime(
+        &self,
+        start: &LocalState,
+        options: &PlanOptions,
+    ) -> Result<Plan, PlanError> {
+        self.make_plan_anytime_with_context(start, options, &mut PlanningContext::new())
+    }
+
+    /// Like [`CompiledDomain::make_plan_anytime`], but reuses the memory in `context` instead of
+    /// allocating it again
+    pub fn make_plan_anytime_with_context(
+        &self,
+        start: &LocalState,
+        options: &PlanOptions,
+        context: &mut PlanningContext,
+    ) -> Result<Plan, PlanError> {
+        self.plan(start, options, true, context)
+    }
+
+    /// Like [`make_plans_with_options`](crate::planner::make_plans_with_options), but for the
+    /// [`Action`]s and [`Goal`] of this domain
+    pub fn make_plans(
+        &self,
+        start: &LocalState,
+        options: &PlanOptions,
+        k: usize,
+    ) -> Result<Vec<Plan>, PlanError> {
+        alternatives::make_plans(self, start, options, k)
+    }
+
+    /// Like [`repair_plan_with_options`](crate::planner::repair_plan_with_options), but for the
+    /// [`Action`]s and [`Goal`] of this domain
+    pub fn repair_plan(
+        &self,
+        state: &LocalState,
+        plan: &[Effect],
+        options: &PlanOptions,
+    ) -> Result<Plan, PlanError> {
+        self.repair_plan_with_context(state, plan, options, &mut PlanningContext::new())
+    }
+
+    /// Like [`CompiledDomain::repair_plan`], but reuses the memory in `context` instead of
+    /// allocating it again
+    pub fn repair_plan_with_context(
+        &self,
+        state: &LocalState,
+        plan: &[Effect],
+        options: &PlanOptions,
+        context: &mut PlanningContext,
+    ) -> Result<Plan, PlanError> {
+        repair::repair(self, state, plan, options, context)
+    }
+
+    fn plan(
+        &self,
+        start: &LocalState,
+        options: &PlanOptions,
+        anytime: bool,
+        context: &mut PlanningContext,
+    ) -> Result<Plan, PlanError> {
+        let state = self.state_from(start);
+        if self.is_goal(&state.values)? {
+            return Err(PlanError::GoalAlreadySatisfied);
+        }
+        let strategy = if self.can_regress() {
+            options.strategy
+        } else {
+            PlanningStrategy::StartToGoal
+        };
+        match strategy {
+            PlanningStrategy::StartToGoal => {
+                let space =
+                    ForwardSearch::new(self, start, options, context.take_free_values(self));
+                let path = search(&space, state, options, anytime, &mut context.forward);
+                context.free_values = space.free_values.into_inner();
+                let path = path?;
+                let mut plan = replay_plan(start, &self.actions, &self.goal, path.actions)?;
+                plan.partial = path.partial;
+                Ok(plan)
+            }
+            PlanningStrategy::GoalToStart => make_plan_regressive(
+                start,
+                &self.actions,
+                &self.goal,
+                options,
+                &mut context.regression,
+            ),
+        }
+    }
+
+    /// Whether [`PlanningStrategy::GoalToStart`] can plan in this domain. Closures, costs that
+    /// depend on the state and comparisons to other keys can't be worked out without the state,
+    /// which regressing doesn't know, and conditions and some mutators can't be regressed.
+    pub(crate) fn can_regress(&self) -> bool {
+        self.goal.conditions.is_empty()
+            && self
+                .goal
+                .requirements
+                .values()
+                .all(|compare| compare.other_key().is_none())
+            && self.actions.iter().all(|action| {
+                action.precondition_fns.is_empty()
+                    && action.conditions.is_empty()
+                    && action
+                        .preconditions
+                        .iter()
+                        .all(|(_, compare)| compare.other_key().is_none())
+                    && action.effects.iter().all(|effect| {
+                        effect.cost_fn.is_none() && effect.mutators.iter().all(can_regress)
+                    })
+            })
+    }
+
+    pub(crate) fn state_from(&self, state: &LocalState) -> State {
+        State::new(
+            self.keys
+                .iter()
+                .map(|key| state.data.get(key).copied())
+                .collect(),
+        )
+    }
+
+    /// Overwrites the values in `state` that have a slot with the ones in `slots`
+    pub(crate) fn write_slots(&self, slots: &[Option<Datum>], state: &mut LocalState) {
+        for (key, value) in self.keys.iter().zip(slots) {
+            match value {
+                Some(value) => {
+                    state.data.insert(*key, *value);
+                }
+                None => {
+                    state.data.remove(key);
+                }
+            }
+        }
+    }
+
+    /// Like [`compare_key`](crate::compare::compare_key), but for the value in a slot
+    pub(crate) fn compare_slot(
+        &self,
+        slots: &[Option<Datum>],
+        slot: usize,
+        comparison: &Compare,
+    ) -> Result<bool, PlanError> {
+        let key = self.keys[slot];
+        // A key that was removed is expected to be missing, so it just doesn't pass
+        let other = comparison.other_key().map(|other| self.slots[&other]);
+        if [Some(slot), other]
+            .into_iter()
+            .flatten()
+            .any(|slot| slots[slot].is_none() && self.removable[slot])
+        {
+            return Ok(false);
+        }
+        let value = slots[slot].as_ref().ok_or(PlanError::MissingKey(key))?;
+        let comparison = comparison.resolve_with(|other| slots[self.slots[&other]])?;
+        compare_values(&comparison, value).map_err(|err| err.for_key(key))
+    }
+
+    /// Checks `conditions` against the values in `slots`, see [`Condition::check`]
+    fn check_conditions(
+        &self,
+        slots: &[Option<Datum>],
+        conditions: &[Condition],
+    ) -> Result<bool, PlanError> {
+        conditions.iter().try_fold(true, |passed, condition| {
+            let holds = condition.check_with(&mut |key, compare| {
+                self.compare_slot(slots, self.slots[&key], compare)
+            })?;
+            Ok(holds && passed)
+        })
+    }
+
+    pub(crate) fn is_goal(&self, slots: &[Option<Datum>]) -> Result<bool, PlanError> {
+        let reached = self
+            .requirements
+            .iter()
+            .try_fold(true, |reached, (slot, compare)| {
+                Ok(self.compare_slot(slots, *slot, compare)? && reached)
+            })?;
+        Ok(self.check_conditions(slots, &self.goal.conditions)? && reached)
+    }
+
+    /// Applies `action` to `state`, if its preconditions pass. The new state reuses one of the
+    /// `free` buffers if there is one. `scratch` is the start state, which the slots are written
+    /// into when the [`Action`] has [`Action::precondition_fns`].
+    pub(crate) fn successor(
+        &self,
+        state: &State,
+        action: &CompiledAction,
+        free: &mut Vec<Box<[Option<Datum>]>>,
+        scratch: &mut LocalState,
+    ) -> Result<Option<State>, PlanError> {
+        // All preconditions are checked, so mistakes are always reported, even when an earlier
+        // one already failed
+        let passed = action
+            .preconditions
+            .iter()
+            .try_fold(true, |passed, (slot, compare)| {
+                Ok(self.compare_slot(&state.values, *slot, compare)? && passed)
+            })?;
+        let conditions = &self.actions[action.action].conditions;
+        if !(self.check_conditions(&state.values, conditions)? && passed) {
+            return Ok(None);
+        }
+        let precondition_fns = &self.actions[action.action].precondition_fns;
+        if !precondition_fns.is_empty() {
+            self.write_slots(&state.values, scratch);
+            if !precondition_fns
+                .iter()
+                .all(|precondition| precondition.check(scratch))
+            {
+                return Ok(None);
+            }
+        }
+        let mut next = state.clone_reusing(free);
+        for (slot, mutator) in &action.mutators {
+            let mut value = next.values[*slot];
+            apply_mutator_to_value(&mut value, mutator, |other| next.values[self.slots[&other]])?;
+            next.set(*slot, value);
+        }
+        Ok(Some(next))
+    }
+}
+
+/// Searches forward from the start state by applying the [`Action`]s
+pub(crate) struct ForwardSearch<'a> {
+    domain: &'a CompiledDomain,
+    heuristic: &'a dyn Heuristic,
+    context: HeuristicContext<'a>,
+    /// The start state, with the slots of the node being estimated written into it, as
+    /// [`Heuristic`]s and [`CostFn`](crate::cost::CostFn)s work on a [`LocalState`]
+    state: RefCell<LocalState>,
+    /// Values of states the search is done with, see [`PlanningContext`]
+    free_values: RefCell<Vec<Box<[Option<Datum>]>>>,
+}
+
+impl<'a> ForwardSearch<'a> {
+    pub(crate) fn new(
+        domain: &'a CompiledDomain,
+        start: &LocalState,
+        options: &'a PlanOptions,
+        free_values: Vec<Box<[Option<Datum>]>>,
+    ) -> Self {
+        Self {
+            domain,
+            heuristic: options.heuristic.as_deref().unwrap_or(&DistanceHeuristic),
+            context: HeuristicContext::new(&domain.actions, &domain.goal),
+            state: RefCell::new(start.clone()),
+            free_values: RefCell::new(free_values),
+        }
+    }
+}
+
+impl SearchSpace for ForwardSearch<'_> {
+    type Node = State;
+
+    fn successors(
+        &self,
+        node: &State,
+        successors: &mut Vec<Successor<State>>,
+    ) -> Result<(), PlanError> {
+        let mut free = self.free_values.borrow_mut();
+        let mut state = self.state.borrow_mut();
+        for action in &self.domain.compiled_actions {
+            if let Some(next) = self.domain.successor(node, action, &mut free, &mut state)? {
+                successors.push(Successor {
+                    node: next,
+                    action: action.action,
+                    cost: self.domain.action_cost(action.action, node, &mut state),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn heuristic(&self, node: &State) -> Cost {
+        let mut state = self.state.borrow_mut();
+        self.domain.write_slots(&node.values, &mut state);
+        self.heuristic.estimate(&state, &self.context)
+    }
+
+    fn is_goal(&self, node: &State) -> Result<bool, PlanError> {
+        self.domain.is_goal(&node.values)
+    }
+
+    fn recycle(&self, node: State) {
+        self.free_values.borrow_mut().push(node.values);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_incremental_hash() {
+        let mut state = State::new(vec![Some(Datum::I64(1)), None, Some(Datum::Bool(true))].into());
+        state.set(0, Some(Datum::I64(2)));
+        state.set(1, Some(Datum::F64(0.5)));
+        let expected = State::new(
+            vec![
+                Some(Datum::I64(2)),
+                Some(Datum::F64(0.5)),
+                Some(Datum::Bool(true)),
+            ]
+            .into(),
+        );
+        assert_eq!(expected.hash, state.hash);
+        assert_eq!(expected, state);
+
+        // The same values in other slots hash differently
+        let swapped = State::new(vec![Some(Datum::I64(1)), Some(Datum::I64(2))].into());
+        let unswapped = State::new(vec![Some(Datum::I64(2)), Some(Datum::I64(1))].into());
+        assert_ne!(swapped.hash, unswapped.hash);
+
+        state.set(1, None);
+        state.set(0, Some(Datum::I64(1)));
+        assert_eq!(
+            State::new(vec![Some(Datum::I64(1)), None, Some(Datum::Bool(true))].into()),
+            state
+        );
+    }
+}
@@ -1,4 +1,8 @@
-use crate::{localstate::LocalState, mutator::Mutator};
+use crate::{
+    cost::{Cost, CostFn},
+    localstate::LocalState,
+    mutator::Mutator,
+};
 use std::hash::{Hash, Hasher};
 
 /// The effect is what happens when an Action is applied.
@@ -13,10 +17,19 @@ pub struct Effect {
     pub action: String,
     /// The [`Mutator`] that are active when this effect is applied.
     pub mutators: Vec<Mutator>,
-    /// An internal state used while path finding
+    /// The state after applying this effect, filled in for the [`Effect`]s of a plan
     pub state: LocalState,
     /// The cost of applying this effect. Default is 1.
-    pub cost: usize,
+    ///
+    /// When there is a [`Self::cost_fn`], this is the least the effect can cost instead.
+    pub cost: Cost,
+    /// Works out the cost from the state the effect is applied to, instead of [`Self::cost`].
+    /// The [`Heuristic`](crate::heuristic::Heuristic)s don't know that state, so they plan with
+    /// [`Self::cost`], but the cost of the [`Plan`](crate::plan::Plan) is always the real one.
+    /// [`PlanningStrategy::GoalToStart`](crate::planner::PlanningStrategy::GoalToStart) can't
+    /// work it out at all, so `StartToGoal` is used instead.
+    #[cfg_attr(feature = "bevy_reflect", reflect(ignore))]
+    pub cost_fn: Option<CostFn>,
 }
 
 impl Effect {
@@ -26,7 +39,8 @@ impl Effect {
             action: action_name.to_string(),
             mutators: vec![],
             state: LocalState::new(),
-            cost: 1,
+            cost: Cost::ONE,
+            cost_fn: None,
         }
     }
 
@@ -35,6 +49,13 @@ impl Effect {
         self.mutators.push(mutator);
         self
     }
+
+    /// The cost of applying this effect to `state`
+    pub fn cost_in(&self, state: &LocalState) -> Cost {
+        self.cost_fn
+            .as_ref()
+            .map_or(self.cost, |cost_fn| cost_fn.cost(state))
+    }
 }
 
 impl Hash for Effect {
@@ -42,5 +63,6 @@ impl Hash for Effect {
         self.mutators.hash(state);
         self.state.hash(state);
         self.cost.hash(state);
+        self.cost_fn.hash(state);
     }
 }
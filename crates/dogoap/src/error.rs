@@ -0,0 +1,190 @@
+use std::fmt::Display;
+use std::time::Duration;
+
+use crate::compare::Compare;
+use crate::condition::Condition;
+use crate::cost::Cost;
+use crate::datum::Datum;
+use crate::key::Key;
+
+/// Everything that can go wrong while coming up with a plan.
+///
+/// Most of these point to a mistake in how the [`Action`](crate::action::Action)s,
+/// [`Goal`](crate::goal::Goal) or [`LocalState`](crate::localstate::LocalState) were defined,
+/// so they're returned instead of panicking, as one bad definition shouldn't be able to
+/// take down the rest of your application.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PlanError {
+    /// A precondition or requirement refers to a key that doesn't exist in the
+    /// [`LocalState`](crate::localstate::LocalState)
+    MissingKey(Key),
+    /// Two [`Datum`]s of different variants were compared or combined, like adding a
+    /// [`Datum::Bool`] to a [`Datum::I64`]
+    TypeMismatch {
+        /// The key the mismatch happened for, if known
+        key: Option<Key>,
+        /// The [`Datum`] on the left-hand side
+        left: Datum,
+        /// The [`Datum`] on the right-hand side
+        right: Datum,
+    },
+    /// A [`Mutator::Divide`](crate::mutator::Mutator::Divide) divided by zero
+    DivisionByZero {
+        /// The key that was divided, if known
+        key: Option<Key>,
+    },
+    /// A [`Datum::I64`] got too big or too small, like from multiplying it over and over
+    Overflow {
+        /// The key that overflowed, if known
+        key: Option<Key>,
+    },
+    /// Every reachable state has been searched without reaching the goal
+    SearchExhausted,
+    /// The search gave up before it could reach the goal, because it hit one of the limits set in
+    /// [`PlanOptions`](crate::planner::PlanOptions)
+    BudgetExceeded(BudgetLimit),
+    /// The start state already satisfies the goal, so there is nothing to plan
+    GoalAlreadySatisfied,
+}
+
+/// The limit from [`PlanOptions`](crate::planner::PlanOptions) that stopped the search
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
+pub enum BudgetLimit {
+    /// Expanded [`PlanOptions::max_expanded_nodes`](crate::planner::PlanOptions::max_expanded_nodes)
+    /// nodes without reaching the goal
+    ExpandedNodes(usize),
+    /// The goal can't be reached in [`PlanOptions::max_plan_length`](crate::planner::PlanOptions::max_plan_length)
+    /// steps or fewer
+    PlanLength(usize),
+    /// The goal can't be reached for [`PlanOptions::max_cost`](crate::planner::PlanOptions::max_cost)
+    /// or less
+    Cost(Cost),
+    /// Searched for longer than [`PlanOptions::timeout`](crate::planner::PlanOptions::timeout)
+    Time(Duration),
+    /// Saw more states than
+    /// [`IncrementalPlanner::with_max_states`](crate::planner::IncrementalPlanner::with_max_states)
+    /// allows without reaching the goal
+    States(usize),
+}
+
+impl Display for BudgetLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ExpandedNodes(max) => write!(f, "expanded the maximum of {max} nodes"),
+            Self::PlanLength(max) => write!(f, "no plan with {max} steps or fewer"),
+            Self::Cost(max) => write!(f, "no plan with a cost of {max} or less"),
+            Self::Time(timeout) => write!(f, "ran out of time after {timeout:?}"),
+            Self::States(max) => write!(f, "saw more than the maximum of {max} states"),
+        }
+    }
+}
+
+impl PlanError {
+    /// Attaches `key` to a [`PlanError::TypeMismatch`], [`PlanError::DivisionByZero`] or
+    /// [`PlanError::Overflow`] that doesn't know its key yet
+    pub(crate) fn for_key(self, key: Key) -> Self {
+        match self {
+            PlanError::DivisionByZero { key: None } => PlanError::DivisionByZero { key: Some(key) },
+            PlanError::Overflow { key: None } => PlanError::Overflow { key: Some(key) },
+            PlanError::TypeMismatch {
+                key: None,
+                left,
+                right,
+            } => PlanError::TypeMismatch {
+                key: Some(key),
+                left,
+                right,
+            },
+            other => other,
+        }
+    }
+}
+
+impl Display for PlanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingKey(key) => write!(f, "Couldn't find key {key:?} in LocalState"),
+            Self::TypeMismatch {
+                key: Some(key),
+                left,
+                right,
+            } => write!(
+                f,
+                "Mismatched Datum types for key {key:?}: {left} and {right}"
+            ),
+            Self::TypeMismatch {
+                key: None,
+                left,
+                right,
+            } => write!(f, "Mismatched Datum types: {left} and {right}"),
+            Self::DivisionByZero { key: Some(key) } => {
+                write!(f, "Divided the value of key {key:?} by zero")
+            }
+            Self::DivisionByZero { key: None } => write!(f, "Divided by zero"),
+            Self::Overflow { key: Some(key) } => {
+                write!(f, "The value of key {key:?} overflowed")
+            }
+            Self::Overflow { key: None } => write!(f, "A value overflowed"),
+            Self::SearchExhausted => {
+                write!(
+                    f,
+                    "Searched every reachable state without reaching the goal"
+                )
+            }
+            Self::BudgetExceeded(limit) => {
+                write!(f, "Gave up searching before reaching the goal: {limit}")
+            }
+            Self::GoalAlreadySatisfied => write!(f, "The goal is already satisfied"),
+        }
+    }
+}
+
+impl std::error::Error for PlanError {}
+
+/// The first step of a plan that can't be executed, from
+/// [`simulate_plan`](crate::planner::simulate_plan)
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimulationError {
+    /// Index of the step in the plan
+    pub step: usize,
+    /// Why the step can't be executed
+    pub reason: StepFailure,
+}
+
+/// Why a step of a plan can't be executed
+#[derive(Clone, Debug, PartialEq)]
+pub enum StepFailure {
+    /// None of the [`Action`](crate::action::Action)s has the name of the step
+    UnknownAction(String),
+    /// The precondition on this key doesn't hold before the step
+    PreconditionFailed(Key, Compare),
+    /// This [`Condition`] doesn't hold before the step
+    ConditionFailed(Condition),
+    /// The [`PreconditionFn`](crate::compare::PreconditionFn) with this name doesn't hold before
+    /// the step
+    PreconditionFnFailed(String),
+    /// Checking a precondition or applying a [`Mutator`](crate::mutator::Mutator) went wrong
+    Error(PlanError),
+}
+
+impl Display for SimulationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Step {} of the plan can't be executed: ", self.step)?;
+        match &self.reason {
+            StepFailure::UnknownAction(action) => write!(f, "there is no action {action:?}"),
+            StepFailure::PreconditionFailed(key, compare) => {
+                write!(f, "precondition {compare:?} on key {key:?} doesn't hold")
+            }
+            StepFailure::ConditionFailed(condition) => {
+                write!(f, "condition {condition:?} doesn't hold")
+            }
+            StepFailure::PreconditionFnFailed(name) => {
+                write!(f, "precondition {name:?} doesn't hold")
+            }
+            StepFailure::Error(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for SimulationError {}
@@ -2,14 +2,19 @@ use std::collections::BTreeMap;
 use std::hash::{Hash, Hasher};
 
 use crate::compare::Compare;
+use crate::condition::Condition;
+use crate::key::Key;
 
-/// Goal is a map of what we want our final [`LocalState`](crate::localstate::LocalState) to be, using String as
+/// Goal is a map of what we want our final [`LocalState`](crate::localstate::LocalState) to be, using [`Key`]s as
 /// keys and [`Compare`] to assert what we want the [`Datum`](crate::datum::Datum) to be
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
 pub struct Goal {
     /// All the requirements needed to be met in order to consider us to be at our final state
-    pub requirements: BTreeMap<String, Compare>,
+    pub requirements: BTreeMap<Key, Compare>,
+    /// Requirements that combine comparisons with `And`, `Or` and `Not`, which need to be met on
+    /// top of [`Self::requirements`]
+    pub conditions: Vec<Condition>,
 }
 
 impl Hash for Goal {
@@ -19,6 +24,7 @@ impl Hash for Goal {
             key.hash(state);
             value.hash(state);
         }
+        self.conditions.hash(state);
     }
 }
 
@@ -33,20 +39,27 @@ impl Goal {
     pub fn new() -> Self {
         Self {
             requirements: BTreeMap::new(),
+            conditions: Vec::new(),
         }
     }
 
     /// Create a new goal with a single requirements
-    pub fn with_req(mut self, key: impl Into<String>, compare: impl Into<Compare>) -> Self {
+    pub fn with_req(mut self, key: impl Into<Key>, compare: impl Into<Compare>) -> Self {
         self.requirements.insert(key.into(), compare.into());
         self
     }
 
+    /// Adds a requirement that combines comparisons, see [`Condition`]
+    pub fn with_condition(mut self, condition: impl Into<Condition>) -> Self {
+        self.conditions.push(condition.into());
+        self
+    }
+
     /// Create a new goal from a list of requirements
-    pub fn from_reqs(preconditions: &[(String, Compare)]) -> Self {
+    pub fn from_reqs<K: Into<Key> + Clone>(preconditions: &[(K, Compare)]) -> Self {
         let mut goal = Goal::new();
         for (k, v) in preconditions {
-            goal = goal.with_req(k, v.clone());
+            goal = goal.with_req(k.clone(), v.clone());
         }
         goal
     }
@@ -0,0 +1,327 @@
+//! Heuristics that guide the planner towards the [`Goal`]
+//!
+//! A [`Heuristic`] estimates how much it'll cost to get from a state to the [`Goal`], and the
+//! planner always continues with the state where the cost so far plus that estimate is the lowest.
+//! As long as the estimate is never more than the real cost, the plan found is the cheapest one.
+//! Estimates closer to the real cost mean fewer states to look at, and estimates that are allowed
+//! to overshoot trade the cheapest plan for a faster search.
+
+use std::cell::OnceCell;
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+use crate::{
+    action::Action,
+    compare::{Compare, compare_distance},
+    condition::Condition,
+    cost::Cost,
+    datum::Datum,
+    goal::Goal,
+    key::Key,
+    localstate::LocalState,
+    mutator::Mutator,
+    relaxed::{Combine, RelaxedDomain},
+};
+
+/// Estimates the cost of reaching the [`Goal`] from a [`LocalState`], see the
+/// [module docs](self). Set it with
+/// [`PlanOptions::with_heuristic`](crate::planner::PlanOptions::with_heuristic).
+///
+/// Only used by [`PlanningStrategy::StartToGoal`](crate::planner::PlanningStrategy::StartToGoal).
+pub trait Heuristic: Debug + Send + Sync {
+    /// Estimates the cost of reaching [`HeuristicContext::goal`] from `state`
+    fn estimate(&self, state: &LocalState, context: &HeuristicContext) -> Cost;
+}
+
+/// What a [`Heuristic`] knows about the problem being planned for. Created once per call to the
+/// planner.
+#[derive(Debug)]
+pub struct HeuristicContext<'a> {
+    /// The [`Action`]s the planner can use
+    pub actions: &'a [Action],
+    /// The [`Goal`] the planner is looking for
+    pub goal: &'a Goal,
+    /// For every key, the biggest change a single [`Action`] makes to it, and the cost of the
+    /// cheapest [`Action`] that changes it at all
+    reach: BTreeMap<Key, (f64, Cost)>,
+    /// Only built once a heuristic that needs it asks for it
+    relaxed: OnceCell<RelaxedDomain>,
+}
+
+impl<'a> HeuristicContext<'a> {
+    /// Creates the context for planning towards `goal` with `actions`
+    pub fn new(actions: &'a [Action], goal: &'a Goal) -> Self {
+        let mut reach: BTreeMap<Key, (f64, Cost)> = BTreeMap::new();
+        for action in actions {
+            let Some(effect) = action.effects.first() else {
+                continue;
+            };
+            for mutator in &effect.mutators {
+                let (key, step) = match mutator {
+                    Mutator::Increment(key, value) | Mutator::Decrement(key, value) => {
+                        match value {
+                            Datum::I64(value) => (key, value.unsigned_abs() as f64),
+                            Datum::F64(value) => (key, value.abs()),
+                            _ => continue,
+                        }
+                    }
+                    // A missing key can't meet any requirement
+                    Mutator::Remove(_) => continue,
+                    // Setting a value closes any gap at once, and so might scaling, clamping,
+                    // copying, toggling or adding another key to it
+                    Mutator::Set(key, _)
+                    | Mutator::Multiply(key, _)
+                    | Mutator::Divide(key, _)
+                    | Mutator::Min(key, _)
+                    | Mutator::Max(key, _)
+                    | Mutator::CopyFrom(key, _)
+                    | Mutator::Toggle(key)
+                    | Mutator::AddKey(key, _) => (key, f64::INFINITY),
+                };
+                let (max_step, min_cost) = reach.entry(*key).or_insert((0.0, effect.cost));
+                *max_step = max_step.max(step);
+                *min_cost = (*min_cost).min(effect.cost);
+            }
+        }
+        Self {
+            actions,
+            goal,
+            reach,
+            relaxed: OnceCell::new(),
+        }
+    }
+
+    pub(crate) fn relaxed(&self) -> &RelaxedDomain {
+        self.relaxed
+            .get_or_init(|| RelaxedDomain::new(self.actions, self.goal))
+    }
+
+    /// For each requirement of the [`Goal`], the cost of the fewest [`Action`]s that could close
+    /// the gap between its value in `state` and the closest one that passes. Never more than what
+    /// it really costs to meet that requirement on its own. The [`Goal::conditions`] aren't
+    /// included, see [`HeuristicContext::condition_cost`].
+    pub fn requirement_costs<'s>(
+        &'s self,
+        state: &'s LocalState,
+    ) -> impl Iterator<Item = (Key, Cost)> + 's {
+        self.goal
+            .requirements
+            .iter()
+            .map(|(key, comparison)| (*key, self.compare_cost(state, *key, comparison)))
+    }
+
+    /// Like [`HeuristicContext::requirement_costs`], but for a [`Condition`]: an `Or` costs as
+    /// much as its cheapest branch, and an `And` as its most expensive one. Never more than what
+    /// it really costs to meet the condition on its own.
+    pub fn condition_cost(&self, state: &LocalState, condition: &Condition) -> Cost {
+        self.weighted_condition_cost(state, condition, &|_| 1.0, Iterator::max)
+    }
+
+    /// The cost of meeting `condition`, with the cost of each comparison multiplied by the weight
+    /// of its key, and the costs of the branches of an `And` combined by `and`
+    fn weighted_condition_cost(
+        &self,
+        state: &LocalState,
+        condition: &Condition,
+        weight: &impl Fn(Key) -> f64,
+        and: fn(std::vec::IntoIter<Cost>) -> Option<Cost>,
+    ) -> Cost {
+        condition.fold(
+            &mut |key, compare| self.compare_cost(state, key, compare) * weight(key),
+            &mut |costs| and(costs.into_iter()).unwrap_or(Cost::ZERO),
+            // Nothing to pick from can never be met
+            &mut |costs| costs.into_iter().min().unwrap_or(Cost::INFINITY),
+        )
+    }
+
+    fn compare_cost(&self, state: &LocalState, key: Key, comparison: &Compare) -> Cost {
+        let distance = match state.data.get(&key) {
+            // Mismatched types are reported when checking if we reached the goal,
+            // here we only need an estimate
+            Some(value) => comparison
+                .resolve(state)
+                .and_then(|comparison| compare_distance(&comparison, value))
+                .unwrap_or(1.0),
+            None => 1.0,
+        };
+        if let Some(other) = comparison.other_key() {
+            // Either side can change, possibly all the way at once, so only one action is sure
+            // to be needed
+            let cheapest = [key, other]
+                .iter()
+                .filter_map(|key| self.reach.get(key))
+                .map(|&(_, cost)| cost)
+                .min();
+            return match cheapest {
+                _ if distance == 0.0 => Cost::ZERO,
+                Some(cost) => cost,
+                None => Cost::new(distance),
+            };
+        }
+        match self.reach.get(&key) {
+            _ if distance == 0.0 => Cost::ZERO,
+            // Any gap takes at least one action, even when setting a value closes it at once.
+            // Rounding errors, like 0.1 + 0.2 being a bit more than 0.3, mustn't add another one.
+            Some(&(step, cost)) if step > 0.0 => {
+                cost * (distance / step * (1.0 - 1e-9)).ceil().max(1.0)
+            }
+            // Nothing changes this key, so the goal can't be reached and any estimate will do
+            _ => Cost::new(distance),
+        }
+    }
+}
+
+/// Always estimates 0, which turns the search into Dijkstra's algorithm. Finds the cheapest plan,
+/// but has to look at every state that is cheaper to reach than the [`Goal`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ZeroHeuristic;
+
+impl Heuristic for ZeroHeuristic {
+    fn estimate(&self, _state: &LocalState, _context: &HeuristicContext) -> Cost {
+        Cost::ZERO
+    }
+}
+
+/// The default. Takes the requirement of the [`Goal`] that is the most expensive to meet, see
+/// [`HeuristicContext::requirement_costs`] and [`HeuristicContext::condition_cost`]. A single
+/// [`Action`] can work towards several
+/// requirements at once, so only the most expensive one is sure to not overestimate, which means
+/// this always finds the cheapest plan.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DistanceHeuristic;
+
+impl Heuristic for DistanceHeuristic {
+    fn estimate(&self, state: &LocalState, context: &HeuristicContext) -> Cost {
+        let conditions = context
+            .goal
+            .conditions
+            .iter()
+            .map(|condition| context.condition_cost(state, condition));
+        context
+            .requirement_costs(state)
+            .map(|(_, cost)| cost)
+            .chain(conditions)
+            .max()
+            .unwrap_or(Cost::ZERO)
+    }
+}
+
+/// Adds up the cost of meeting every requirement of the [`Goal`], each multiplied by the weight
+/// of its key. Only the cheapest branch of an `Or` in a [`Condition`] counts. Usually searches a
+/// lot less than [`DistanceHeuristic`], but can overestimate, so the plan isn't guaranteed to be
+/// the cheapest one.
+#[derive(Clone, Debug)]
+pub struct WeightedDistanceHeuristic {
+    /// The weight of each key
+    pub weights: BTreeMap<Key, f64>,
+    /// The weight of keys that aren't in [`Self::weights`]
+    pub default_weight: f64,
+}
+
+impl Default for WeightedDistanceHeuristic {
+    fn default() -> Self {
+        Self {
+            weights: BTreeMap::new(),
+            default_weight: 1.0,
+        }
+    }
+}
+
+impl WeightedDistanceHeuristic {
+    /// Creates a new [`WeightedDistanceHeuristic`] where every key has a weight of 1
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the weight of `key`
+    pub fn with_weight(mut self, key: impl Into<Key>, weight: f64) -> Self {
+        self.weights.insert(key.into(), weight);
+        self
+    }
+
+    /// Sets the weight of keys that don't have their own
+    pub fn with_default_weight(mut self, weight: f64) -> Self {
+        self.default_weight = weight;
+        self
+    }
+}
+
+impl Heuristic for WeightedDistanceHeuristic {
+    fn estimate(&self, state: &LocalState, context: &HeuristicContext) -> Cost {
+        let weight = |key| *self.weights.get(&key).unwrap_or(&self.default_weight);
+        let conditions = context.goal.conditions.iter().map(|condition| {
+            context.weighted_condition_cost(state, condition, &weight, |costs| Some(costs.sum()))
+        });
+        context
+            .requirement_costs(state)
+            .map(|(key, cost)| cost * weight(key))
+            .chain(conditions)
+            .sum()
+    }
+}
+
+/// Weighted A*: multiplies the estimate of another [`Heuristic`] by `1 + epsilon`. The plan found
+/// costs at most `1 + epsilon` times as much as the cheapest one, but the search is faster the
+/// higher `epsilon` is.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WeightedAStar<H> {
+    /// The [`Heuristic`] whose estimate gets inflated
+    pub heuristic: H,
+    /// How much more than the cheapest plan we're willing to pay, `0.5` for up to 50% more
+    pub epsilon: f64,
+}
+
+impl<H: Heuristic> WeightedAStar<H> {
+    /// Creates a new [`WeightedAStar`] inflating `heuristic` by `1 + epsilon`
+    pub fn new(heuristic: H, epsilon: f64) -> Self {
+        Self { heuristic, epsilon }
+    }
+}
+
+impl<H: Heuristic> Heuristic for WeightedAStar<H> {
+    fn estimate(&self, state: &LocalState, context: &HeuristicContext) -> Cost {
+        self.heuristic.estimate(state, context) * (1.0 + self.epsilon)
+    }
+}
+
+/// `h_max`: the cost of the most expensive requirement of the [`Goal`], where the cost of each
+/// precondition or requirement is found by chaining [`Action`]s in a relaxed version of the problem
+/// in which values are never taken away. Unlike [`DistanceHeuristic`] it sees that an [`Action`]
+/// can't help before its preconditions are met. Never overestimates, so it always finds the
+/// cheapest plan.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HMaxHeuristic;
+
+impl Heuristic for HMaxHeuristic {
+    fn estimate(&self, state: &LocalState, context: &HeuristicContext) -> Cost {
+        let relaxed = context.relaxed();
+        relaxed.goal_cost(&relaxed.costs(state, Combine::Max), Combine::Max)
+    }
+}
+
+/// `h_add`: like [`HMaxHeuristic`], but adds up the costs of all preconditions and requirements
+/// instead of taking the most expensive one. Much better informed, so it searches a lot less, but
+/// counts [`Action`]s that help with several of them more than once, so the plan isn't guaranteed
+/// to be the cheapest one.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HAddHeuristic;
+
+impl Heuristic for HAddHeuristic {
+    fn estimate(&self, state: &LocalState, context: &HeuristicContext) -> Cost {
+        let relaxed = context.relaxed();
+        relaxed.goal_cost(&relaxed.costs(state, Combine::Add), Combine::Add)
+    }
+}
+
+/// FF: extracts a plan for the relaxed problem the way [`HAddHeuristic`] finds it, and takes the
+/// cost of its [`Action`]s, counting each one only as often as it's needed. Usually the closest to
+/// the real cost, but can still overestimate.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FfHeuristic;
+
+impl Heuristic for FfHeuristic {
+    fn estimate(&self, state: &LocalState, context: &HeuristicContext) -> Cost {
+        let relaxed = context.relaxed();
+        relaxed.relaxed_plan_cost(&mut relaxed.costs(state, Combine::Add))
+    }
+}
@@ -0,0 +1,573 @@
+//! D* Lite, which keeps its search between plans and only repairs the parts of it that changed
+//!
+//! Like [`PlanningStrategy::GoalToStart`](crate::planner::PlanningStrategy::GoalToStart), the
+//! search starts at the requirements of the [`Goal`](crate::goal::Goal) and regresses them
+//! through the [`Action`](crate::action::Action)s until the start state meets them. Every node
+//! keeps two costs: `g`, the cost from it to the goal that the search has settled on, and `rhs`,
+//! the cost going by what the nodes it leads to have settled on. Only nodes where the two differ
+//! need to be looked at again, so after the cost of an action changes, the search picks up the
+//! nodes it affects instead of starting over.
+//!
+//! Neither cost depends on the start state, only the estimates do, so they're all kept when the
+//! start moves. Instead of sorting the queue again for the new estimates, the key modifier `km`
+//! is raised by the most any estimate can have dropped, which keeps every key in the queue from
+//! being more than the real one.
+//! All nodes the start state meets lead from one shared start vertex for free, which gives the
+//! search a single start.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+use std::time::Instant;
+
+use crate::{
+    cost::Cost,
+    domain::{CompiledDomain, State},
+    error::{BudgetLimit, PlanError},
+    localstate::LocalState,
+    planner::{Plan, PlanOptions, replay_plan},
+    regression::RegressionNode,
+    search::FxIndexMap,
+};
+
+const INFINITY: Cost = Cost::INFINITY;
+
+/// Stands in for the shared start vertex wherever a vertex index is expected
+const START: u32 = u32::MAX;
+
+/// The index of the node with the requirements of the goal, which is added first
+const GOAL: u32 = 0;
+
+/// How many states an [`IncrementalPlanner`] keeps unless told otherwise
+const DEFAULT_MAX_STATES: usize = 100_000;
+
+struct Vertex {
+    g: Cost,
+    rhs: Cost,
+    heuristic: Cost,
+    /// Whether the start state meets the requirements of this node
+    is_start: bool,
+    /// The nodes this one was regressed from, and the index of the action that leads there
+    successors: Vec<(u32, u32)>,
+    /// The nodes that regressing this one gives, and the index of the action that leads here.
+    /// `None` until the vertex is expanded for the first time.
+    predecessors: Option<Vec<(u32, u32)>>,
+}
+
+impl Vertex {
+    fn new(heuristic: Cost, is_start: bool) -> Self {
+        Self {
+            g: INFINITY,
+            rhs: INFINITY,
+            heuristic,
+            is_start,
+            successors: Vec::new(),
+            predecessors: None,
+        }
+    }
+}
+
+/// The vertex that leads to every node the start state meets for free
+struct StartVertex {
+    g: Cost,
+    rhs: Cost,
+    successors: Vec<u32>,
+}
+
+impl Default for StartVertex {
+    fn default() -> Self {
+        Self {
+            g: INFINITY,
+            rhs: INFINITY,
+            successors: Vec::new(),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct QueueEntry {
+    key: (Cost, Cost),
+    vertex: u32,
+}
+
+/// Estimates how much it costs to get from the start state to a node
+struct Estimator<'a> {
+    domain: &'a CompiledDomain,
+    start: &'a LocalState,
+    /// The cost of the cheapest action, divided by the most mutators any action has
+    per_key: Cost,
+}
+
+impl<'a> Estimator<'a> {
+    fn new(domain: &'a CompiledDomain, start: &'a LocalState) -> Self {
+        let effects = domain
+            .actions()
+            .iter()
+            .filter_map(|action| action.effects.first());
+        let cheapest = effects
+            .clone()
+            .map(|effect| effect.cost)
+            .min()
+            .filter(|cost| cost.is_finite())
+            .unwrap_or(Cost::ZERO);
+        let most_mutators = effects
+            .map(|effect| effect.mutators.len())
+            .max()
+            .unwrap_or(1)
+            .max(1);
+        Self {
+            domain,
+            start,
+            per_key: cheapest * (1.0 / most_mutators as f64),
+        }
+    }
+
+    /// Every key with an unmet requirement needs a mutator to change it. Unlike the estimate of
+    /// `GoalToStart`, this never drops by more than the cost of the action a node is regressed
+    /// through, which D* Lite needs to find the cheapest plan.
+    fn estimate(&self, node: &RegressionNode) -> Cost {
+        self.per_key * node.unmet_keys(self.start) as f64
+    }
+
+    /// The cost of the action at `action` in [`CompiledDomain::actions`]
+    fn cost(&self, action: u32) -> Cost {
+        self.domain.actions()[action as usize].effects[0].cost
+    }
+}
+
+#[derive(Default)]
+struct Graph {
+    vertices: FxIndexMap<RegressionNode, Vertex>,
+    start: StartVertex,
+    /// Every inconsistent vertex, along with entries that went stale since they were pushed
+    queue: BinaryHeap<Reverse<QueueEntry>>,
+    /// Added to every key, and raised whenever the start moves
+    km: Cost,
+}
+
+impl Graph {
+    fn costs(&self, vertex: u32) -> (Cost, Cost, Cost) {
+        if vertex == START {
+            (self.start.g, self.start.rhs, Cost::ZERO)
+        } else {
+            let vertex = &self.vertices[vertex as usize];
+            (vertex.g, vertex.rhs, vertex.heuristic)
+        }
+    }
+
+    fn key(&self, vertex: u32) -> (Cost, Cost) {
+        let (g, rhs, heuristic) = self.costs(vertex);
+        let cost = g.min(rhs);
+        (cost + heuristic + self.km, cost)
+    }
+
+    fn is_consistent(&self, vertex: u32) -> bool {
+        let (g, rhs, _) = self.costs(vertex);
+        g == rhs
+    }
+
+    fn push(&mut self, vertex: u32) {
+        let key = self.key(vertex);
+        self.queue.push(Reverse(QueueEntry { key, vertex }));
+    }
+
+    /// The key of the first vertex in the queue, dropping the stale entries in front of it
+    fn top_key(&mut self) -> (Cost, Cost) {
+        while let Some(Reverse(entry)) = self.queue.peek() {
+            let vertex = entry.vertex;
+            if self.is_consistent(vertex) {
+                self.queue.pop();
+                continue;
+            }
+            let (old, key) = (entry.key, self.key(vertex));
+            if old == key {
+                return key;
+            }
+            self.queue.pop();
+            // Keys only go up when the start moves, which is caught up on here. A key that went
+            // down was pushed again when it did.
+            if old < key {
+                self.push(vertex);
+            }
+        }
+        (INFINITY, INFINITY)
+    }
+
+    /// Sorts the queue again, for when the estimates might have dropped by more than
+    /// [`Graph::km`] covers
+    fn requeue(&mut self) {
+        let mut inconsistent: Vec<u32> = std::mem::take(&mut self.queue)
+            .into_iter()
+            .map(|Reverse(entry)| entry.vertex)
+            .collect();
+        inconsistent.sort_unstable();
+        inconsistent.dedup();
+        for vertex in inconsistent {
+            if !self.is_consistent(vertex) {
+                self.push(vertex);
+            }
+        }
+    }
+
+    /// Recalculates the `rhs` of `vertex` from its successors, and queues it if it's
+    /// inconsistent
+    fn update_vertex(&mut self, vertex: u32, estimator: &Estimator) {
+        if vertex == START {
+            self.start.rhs = self
+                .start
+                .successors
+                .iter()
+                .map(|&successor| self.vertices[successor as usize].g)
+                .min()
+                .unwrap_or(INFINITY);
+        } else if vertex != GOAL {
+            let rhs = self.vertices[vertex as usize]
+                .successors
+                .iter()
+                .map(|&(successor, action)| {
+                    estimator.cost(action) + self.vertices[successor as usize].g
+                })
+                .min()
+                .unwrap_or(INFINITY);
+            self.vertices[vertex as usize].rhs = rhs;
+        }
+        if !self.is_consistent(vertex) {
+            self.push(vertex);
+        }
+    }
+
+    /// Returns the index of `node`, adding it if it's new
+    fn vertex(&mut self, node: RegressionNode, estimator: &Estimator) -> Result<u32, PlanError> {
+        let index = match self.vertices.get_index_of(&node) {
+            Some(index) => index,
+            None => {
+                let heuristic = estimator.estimate(&node);
+                let is_start = node.is_satisfied_by(estimator.start)?;
+                let index = self
+                    .vertices
+                    .insert_full(node, Vertex::new(heuristic, is_start))
+                    .0;
+                if is_start {
+                    self.start.successors.push(Self::index(index));
+                }
+                index
+            }
+        };
+        Ok(Self::index(index))
+    }
+
+    fn index(index: usize) -> u32 {
+        u32::try_from(index).expect("Searched more states than fit in a u32")
+    }
+
+    /// The predecessors of `vertex`, which are regressed the first time it's expanded
+    fn predecessors(
+        &mut self,
+        vertex: u32,
+        estimator: &Estimator,
+    ) -> Result<Vec<(u32, u32)>, PlanError> {
+        if let Some(predecessors) = &self.vertices[vertex as usize].predecessors {
+            return Ok(predecessors.clone());
+        }
+        let (node, _) = self
+            .vertices
+            .get_index(vertex as usize)
+            .expect("Every vertex index should point to a node");
+        let node = node.clone();
+        let mut predecessors = Vec::new();
+        for (index, action) in estimator.domain.actions().iter().enumerate() {
+            // Only actions that contribute to a requirement are worth regressing through
+            let Some((regressed, true)) = node.regress(action)? else {
+                continue;
+            };
+            let predecessor = self.vertex(regressed, estimator)?;
+            let action = u32::try_from(index).expect("More actions than fit in a u32");
+            self.vertices[predecessor as usize]
+                .successors
+                .push((vertex, action));
+            predecessors.push((predecessor, action));
+        }
+        self.vertices[vertex as usize].predecessors = Some(predecessors.clone());
+        Ok(predecessors)
+    }
+
+    /// Works out the estimates and which nodes the start state meets again, for a new start
+    /// state or new action costs. The costs found so far are kept.
+    fn move_start(&mut self, estimator: &Estimator) -> Result<(), PlanError> {
+        self.start.successors.clear();
+        for index in 0..self.vertices.len() {
+            let (node, _) = self
+                .vertices
+                .get_index(index)
+                .expect("The index is in bounds");
+            let heuristic = estimator.estimate(node);
+            let is_start = node.is_satisfied_by(estimator.start)?;
+            let vertex = &mut self.vertices[index];
+            vertex.heuristic = heuristic;
+            vertex.is_start = is_start;
+            if is_start {
+                self.start.successors.push(Self::index(index));
+            }
+        }
+        self.update_vertex(START, estimator);
+        Ok(())
+    }
+
+    /// Expands vertices until the cheapest path from the start vertex is known, or there is none
+    fn compute_shortest_path(
+        &mut self,
+        estimator: &Estimator,
+        options: &PlanOptions,
+        max_states: usize,
+    ) -> Result<(), PlanError> {
+        // Only ask for the time when there is a timeout, not every platform has a clock
+        let started = options.timeout.map(|timeout| (Instant::now(), timeout));
+        let mut expanded = 0;
+        loop {
+            // Unlike plain D* Lite, vertices with the same key as the start vertex are expanded
+            // too: the nodes the start state meets lead from it for free and have the same key
+            let top = self.top_key();
+            if top == (INFINITY, INFINITY) || (top > self.key(START) && self.is_consistent(START)) {
+                break;
+            }
+            if let Some(max) = options.max_expanded_nodes
+                && expanded >= max
+            {
+                return Err(PlanError::BudgetExceeded(BudgetLimit::ExpandedNodes(max)));
+            }
+            if let Some((started, timeout)) = started
+                && started.elapsed() >= timeout
+            {
+                return Err(PlanError::BudgetExceeded(BudgetLimit::Time(timeout)));
+            }
+            if self.vertices.len() > max_states {
+                return Err(PlanError::BudgetExceeded(BudgetLimit::States(max_states)));
+            }
+            expanded += 1;
+
+            let Some(Reverse(QueueEntry { vertex, .. })) = self.queue.pop() else {
+                break;
+            };
+            let (g, rhs, _) = self.costs(vertex);
+            if vertex == START {
+                if g > rhs {
+                    self.start.g = rhs;
+                } else {
+                    self.start.g = INFINITY;
+                    self.update_vertex(START, estimator);
+                }
+                continue;
+            }
+            let predecessors = self.predecessors(vertex, estimator)?;
+            if g > rhs {
+                self.vertices[vertex as usize].g = rhs;
+            } else {
+                self.vertices[vertex as usize].g = INFINITY;
+                self.update_vertex(vertex, estimator);
+            }
+            for (predecessor, _) in predecessors {
+                self.update_vertex(predecessor, estimator);
+            }
+            if self.vertices[vertex as usize].is_start {
+                self.update_vertex(START, estimator);
+            }
+        }
+        Ok(())
+    }
+
+    /// The indices of the actions on the cheapest path from the start vertex to the goal
+    fn path(&self, estimator: &Estimator) -> Result<Vec<usize>, PlanError> {
+        let mut current = self
+            .start
+            .successors
+            .iter()
+            .copied()
+            .min_by_key(|&successor| self.vertices[successor as usize].g)
+            .ok_or(PlanError::SearchExhausted)?;
+        let mut steps = Vec::new();
+        let mut seen = HashSet::new();
+        while current != GOAL {
+            seen.insert(current);
+            let (successor, action) = self.vertices[current as usize]
+                .successors
+                .iter()
+                .copied()
+                .filter(|(successor, _)| !seen.contains(successor))
+                .min_by_key(|&(successor, action)| {
+                    estimator.cost(action) + self.vertices[successor as usize].g
+                })
+                .ok_or(PlanError::SearchExhausted)?;
+            steps.push(action as usize);
+            current = successor;
+        }
+        Ok(steps)
+    }
+}
+
+/// Plans for the same [`Action`](crate::action::Action)s and [`Goal`](crate::goal::Goal) over and
+/// over, keeping the search between plans
+///
+/// Uses D* Lite, which searches back from the [`Goal`](crate::goal::Goal) like
+/// [`PlanningStrategy::GoalToStart`](crate::planner::PlanningStrategy::GoalToStart) does. The
+/// costs it finds don't depend on the start state, so when only the start changes, like after
+/// executing a step of the last plan, most of the search is kept. After the cost of an
+/// [`Action`](crate::action::Action) changes with [`IncrementalPlanner::set_action_cost`], only
+/// the part of the search that depends on it is looked at again.
+///
+/// In a domain that can't be regressed, see `GoalToStart`, every plan is made from scratch.
+/// Once the search has seen more states than [`IncrementalPlanner::with_max_states`] allows, it
+/// gives up, and the next plan starts over.
+pub struct IncrementalPlanner {
+    domain: CompiledDomain,
+    graph: Graph,
+    /// Actions whose cost changed since the last plan
+    changed_actions: Vec<usize>,
+    /// The start state of the last plan
+    last_start: Option<State>,
+    max_states: usize,
+}
+
+impl IncrementalPlanner {
+    /// Creates an [`IncrementalPlanner`] for the [`Action`](crate::action::Action)s and
+    /// [`Goal`](crate::goal::Goal) of `domain`
+    pub fn new(domain: CompiledDomain) -> Self {
+        Self {
+            domain,
+            graph: Graph::default(),
+            changed_actions: Vec::new(),
+            last_start: None,
+            max_states: DEFAULT_MAX_STATES,
+        }
+    }
+
+    /// Keeps at most `max` states between plans, 100 000 by default. Once the search has seen
+    /// more than that, it stops with [`BudgetLimit::States`], and the next plan starts from
+    /// scratch.
+    pub fn with_max_states(mut self, max: usize) -> Self {
+        self.max_states = max;
+        self
+    }
+
+    /// The [`CompiledDomain`] this planner plans in
+    pub fn domain(&self) -> &CompiledDomain {
+        &self.domain
+    }
+
+    /// How many states the search has seen so far
+    pub fn states(&self) -> usize {
+        self.graph.vertices.len()
+    }
+
+    /// Changes the cost of every [`Action`](crate::action::Action) called `action`. The next plan
+    /// takes it into account without starting over.
+    pub fn set_action_cost(&mut self, action: &str, cost: impl Into<Cost>) {
+        let cost = cost.into();
+        let changed: Vec<usize> = self
+            .domain
+            .actions()
+            .iter()
+            .enumerate()
+            .filter(|(_, candidate)| {
+                candidate.key == action
+                    && candidate
+                        .effects
+                        .first()
+                        .is_some_and(|effect| effect.cost != cost)
+            })
+            .map(|(index, _)| index)
+            .collect();
+        for index in changed {
+            self.domain.set_action_cost(index, cost);
+            self.changed_actions.push(index);
+        }
+    }
+
+    /// Forgets every state seen so far, so the next plan starts from scratch
+    pub fn reset(&mut self) {
+        self.graph = Graph::default();
+        self.changed_actions.clear();
+        self.last_start = None;
+    }
+
+    /// Like [`CompiledDomain::make_plan`], repairing the last search instead of starting over
+    ///
+    /// Only [`PlanOptions::max_expanded_nodes`] and [`PlanOptions::timeout`] are used. When one of
+    /// them is hit, the next plan continues where this one stopped. When the search sees more
+    /// states than [`IncrementalPlanner::with_max_states`] allows, it returns
+    /// [`BudgetLimit::States`] and the next plan starts over.
+    pub fn make_plan(
+        &mut self,
+        start: &LocalState,
+        options: &PlanOptions,
+    ) -> Result<Plan, PlanError> {
+        let state = self.domain.state_from(start);
+        if self.domain.is_goal(&state.values)? {
+            return Err(PlanError::GoalAlreadySatisfied);
+        }
+        if !self.domain.can_regress() {
+            return self.domain.make_plan(start, options);
+        }
+        if self.graph.vertices.len() > self.max_states {
+            self.reset();
+        }
+
+        let estimator = Estimator::new(&self.domain, start);
+        let graph = &mut self.graph;
+        match &self.last_start {
+            None => {
+                let goal =
+                    graph.vertex(RegressionNode::from_goal(self.domain.goal()), &estimator)?;
+                graph.vertices[goal as usize].rhs = Cost::ZERO;
+                graph.push(goal);
+            }
+            Some(_) if !self.changed_actions.is_empty() => {
+                // The estimates go by the cheapest action, so they might have dropped by any
+                // amount
+                graph.move_start(&estimator)?;
+                graph.requeue();
+                let mut changed = Vec::new();
+                for (index, vertex) in graph.vertices.values().enumerate() {
+                    if vertex
+                        .successors
+                        .iter()
+                        .any(|(_, action)| self.changed_actions.contains(&(*action as usize)))
+                    {
+                        changed.push(Graph::index(index));
+                    }
+                }
+                for vertex in changed {
+                    graph.update_vertex(vertex, &estimator);
+                }
+            }
+            Some(last_start) if last_start.values != state.values => {
+                // An estimate only drops when the value of a key it counts changed
+                let moved = last_start
+                    .values
+                    .iter()
+                    .zip(&state.values)
+                    .filter(|(last, value)| last != value)
+                    .count();
+                graph.km += estimator.per_key * moved as f64;
+                graph.move_start(&estimator)?;
+            }
+            Some(_) => {}
+        }
+        self.changed_actions.clear();
+        self.last_start = Some(state);
+
+        graph.compute_shortest_path(&estimator, options, self.max_states)?;
+        if graph.start.g == INFINITY {
+            return Err(PlanError::SearchExhausted);
+        }
+        let steps = graph.path(&estimator)?;
+        replay_plan(start, self.domain.actions(), self.domain.goal(), steps)
+    }
+}
+
+impl std::fmt::Debug for IncrementalPlanner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IncrementalPlanner")
+            .field("domain", &self.domain)
+            .field("states", &self.graph.vertices.len())
+            .finish_non_exhaustive()
+    }
+}
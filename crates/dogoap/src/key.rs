@@ -0,0 +1,166 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Display};
+use std::hash::{Hash, Hasher};
+use std::sync::{OnceLock, PoisonError, RwLock};
+
+/// The name of a value in a [`LocalState`](crate::localstate::LocalState), like `"energy"`.
+///
+/// Keys are interned: every name is stored once for the lifetime of the program, and a `Key` is
+/// just a handle to it. That makes them as cheap to copy, check for equality and hash as an
+/// integer, which matters as the planner does all three for every state it looks at. Anything
+/// that takes a key accepts a `&str` or [`String`] as well.
+///
+/// Keys are ordered alphabetically by their name, so anything sorted by key comes out the same
+/// way every time. Ordering two different keys compares their names, so a lookup in a map keyed
+/// by `Key` still costs a few string comparisons; only finding the matching key is an integer
+/// comparison.
+#[derive(Clone, Copy)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(bevy_reflect::Reflect),
+    reflect(opaque, Clone, Debug, PartialEq, Hash)
+)]
+pub struct Key {
+    /// The order the name was first used in, which is unique per name
+    id: u32,
+    name: &'static str,
+}
+
+#[derive(Default)]
+struct Interner {
+    keys: HashMap<&'static str, Key>,
+}
+
+fn interner() -> &'static RwLock<Interner> {
+    static INTERNER: OnceLock<RwLock<Interner>> = OnceLock::new();
+    INTERNER.get_or_init(RwLock::default)
+}
+
+impl Key {
+    /// Returns the key for `name`, interning it if this is the first time it's used
+    pub fn new(name: &str) -> Self {
+        // Nothing can panic while the lock is held, so a poisoned lock is still consistent
+        if let Some(&key) = interner()
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .keys
+            .get(name)
+        {
+            return key;
+        }
+        let mut interner = interner().write().unwrap_or_else(PoisonError::into_inner);
+        // Another thread might have interned it while we waited for the lock
+        if let Some(&key) = interner.keys.get(name) {
+            return key;
+        }
+        let key = Key {
+            id: u32::try_from(interner.keys.len()).expect("Too many keys to intern"),
+            name: Box::leak(name.into()),
+        };
+        interner.keys.insert(key.name, key);
+        key
+    }
+
+    /// The name this key was created from
+    pub fn as_str(self) -> &'static str {
+        self.name
+    }
+}
+
+impl PartialEq for Key {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Key {}
+
+impl PartialOrd for Key {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Key {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if self.id == other.id {
+            Ordering::Equal
+        } else {
+            self.name.cmp(other.name)
+        }
+    }
+}
+
+impl Hash for Key {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl From<&str> for Key {
+    fn from(name: &str) -> Self {
+        Key::new(name)
+    }
+}
+
+impl From<&String> for Key {
+    fn from(name: &String) -> Self {
+        Key::new(name)
+    }
+}
+
+impl From<String> for Key {
+    fn from(name: String) -> Self {
+        Key::new(&name)
+    }
+}
+
+impl PartialEq<str> for Key {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Key {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Debug for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(self.as_str(), f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interning() {
+        let key = Key::new("energy");
+        assert_eq!(key, Key::from("energy"));
+        assert_eq!(key, Key::from(String::from("energy")));
+        assert_ne!(key, Key::new("hunger"));
+        assert_eq!("energy", key.as_str());
+        assert_eq!(key, "energy");
+        assert_eq!("energy", key.to_string());
+        assert_eq!("\"energy\"", format!("{key:?}"));
+    }
+
+    #[test]
+    fn test_ordered_by_name() {
+        let later = Key::new("key_test_b");
+        let earlier = Key::new("key_test_a");
+        assert!(earlier < later);
+        assert_eq!(Ordering::Equal, later.cmp(&Key::new("key_test_b")));
+    }
+}
@@ -1,12 +1,25 @@
 #![doc = include_str!("../README.md")]
 mod action;
+mod alternatives;
 mod compare;
+mod condition;
+mod cost;
 mod datum;
+mod domain;
 mod effect;
+mod error;
 mod goal;
+mod incremental;
+mod key;
 mod localstate;
 mod mutator;
+mod plan;
+mod regression;
+mod relaxed;
+mod repair;
+mod search;
 
+pub mod heuristic;
 pub mod planner;
 pub mod prelude;
 pub mod simple;
@@ -1,51 +1,192 @@
-use crate::{datum::Datum, localstate::InternalData};
+use crate::{datum::Datum, error::PlanError, key::Key, localstate::InternalData};
 
 /// Describes a change in [`LocalState`](crate::localstate::LocalState), based on
-/// the String key + a [`Datum`]
+/// the [`Key`] + a [`Datum`]
 #[derive(Clone, Debug, PartialEq, Hash, Eq)]
 #[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
 pub enum Mutator {
     /// Set a value for a key
-    Set(String, Datum), // :key, :value
+    Set(Key, Datum), // :key, :value
     /// Increment a value for a key by a given amount
-    Increment(String, Datum), // :key, :increment-by
+    Increment(Key, Datum), // :key, :increment-by
     /// Decrement a value for a key by a given amount
-    Decrement(String, Datum), // :key, :decrement-by
+    Decrement(Key, Datum), // :key, :decrement-by
+    /// Multiply a value for a key by a given amount
+    Multiply(Key, Datum), // :key, :multiply-by
+    /// Divide a value for a key by a given amount. A [`Datum::I64`] is rounded towards zero.
+    Divide(Key, Datum), // :key, :divide-by
+    /// Cap a value for a key, so it's at most the given value
+    Min(Key, Datum), // :key, :at-most
+    /// Raise a value for a key, so it's at least the given value
+    Max(Key, Datum), // :key, :at-least
+    /// Set a key to the value of another key
+    CopyFrom(Key, Key), // :key, :other-key
+    /// Flip a [`Datum::Bool`] for a key
+    Toggle(Key), // :key
+    /// Increment a value for a key by the value of another key
+    AddKey(Key, Key), // :key, :other-key
+    /// Remove a key, so it's missing from the state afterwards. While it's missing, comparisons
+    /// on it fail when planning, instead of returning [`PlanError::MissingKey`].
+    Remove(Key), // :key
 }
 
 impl Mutator {
     /// Convenience method for creating a [`Mutator::Set`]
-    pub fn set(key: impl Into<String>, value: impl Into<Datum>) -> Self {
+    pub fn set(key: impl Into<Key>, value: impl Into<Datum>) -> Self {
         Mutator::Set(key.into(), value.into())
     }
 
     /// Convenience method for creating a [`Mutator::Increment`]
-    pub fn increment(key: impl Into<String>, value: impl Into<Datum>) -> Self {
+    pub fn increment(key: impl Into<Key>, value: impl Into<Datum>) -> Self {
         Mutator::Increment(key.into(), value.into())
     }
 
     /// Convenience method for creating a [`Mutator::Decrement`]
-    pub fn decrement(key: impl Into<String>, value: impl Into<Datum>) -> Self {
+    pub fn decrement(key: impl Into<Key>, value: impl Into<Datum>) -> Self {
         Mutator::Decrement(key.into(), value.into())
     }
+
+    /// Convenience method for creating a [`Mutator::Multiply`]
+    pub fn multiply(key: impl Into<Key>, value: impl Into<Datum>) -> Self {
+        Mutator::Multiply(key.into(), value.into())
+    }
+
+    /// Convenience method for creating a [`Mutator::Divide`]
+    pub fn divide(key: impl Into<Key>, value: impl Into<Datum>) -> Self {
+        Mutator::Divide(key.into(), value.into())
+    }
+
+    /// Convenience method for creating a [`Mutator::Min`]
+    pub fn min(key: impl Into<Key>, value: impl Into<Datum>) -> Self {
+        Mutator::Min(key.into(), value.into())
+    }
+
+    /// Convenience method for creating a [`Mutator::Max`]
+    pub fn max(key: impl Into<Key>, value: impl Into<Datum>) -> Self {
+        Mutator::Max(key.into(), value.into())
+    }
+
+    /// Convenience method for creating a [`Mutator::CopyFrom`]
+    pub fn copy_from(key: impl Into<Key>, other: impl Into<Key>) -> Self {
+        Mutator::CopyFrom(key.into(), other.into())
+    }
+
+    /// Convenience method for creating a [`Mutator::Toggle`]
+    pub fn toggle(key: impl Into<Key>) -> Self {
+        Mutator::Toggle(key.into())
+    }
+
+    /// Convenience method for creating a [`Mutator::AddKey`]
+    pub fn add_key(key: impl Into<Key>, other: impl Into<Key>) -> Self {
+        Mutator::AddKey(key.into(), other.into())
+    }
+
+    /// Convenience method for creating a [`Mutator::Remove`]
+    pub fn remove(key: impl Into<Key>) -> Self {
+        Mutator::Remove(key.into())
+    }
 }
 
-pub fn apply_mutator(data: &mut InternalData, mutator: &Mutator) {
-    match mutator {
-        Mutator::Set(key, value) => {
-            data.insert(key.to_string(), *value);
+impl Mutator {
+    /// The key this mutator changes
+    pub fn key(&self) -> Key {
+        match self {
+            Mutator::Set(key, _)
+            | Mutator::Increment(key, _)
+            | Mutator::Decrement(key, _)
+            | Mutator::Multiply(key, _)
+            | Mutator::Divide(key, _)
+            | Mutator::Min(key, _)
+            | Mutator::Max(key, _)
+            | Mutator::CopyFrom(key, _)
+            | Mutator::Toggle(key)
+            | Mutator::AddKey(key, _)
+            | Mutator::Remove(key) => *key,
         }
-        Mutator::Increment(key, value) => {
-            if let Some(current_value) = data.get_mut(key) {
-                *current_value += *value;
-            }
+    }
+
+    /// The other key a [`Mutator::CopyFrom`] or [`Mutator::AddKey`] reads from
+    pub fn other_key(&self) -> Option<Key> {
+        match self {
+            Mutator::CopyFrom(_, other) | Mutator::AddKey(_, other) => Some(*other),
+            _ => None,
         }
-        Mutator::Decrement(key, value) => {
-            if let Some(current_value) = data.get_mut(key) {
-                *current_value -= *value;
+    }
+}
+
+/// Applies `mutator` to `data`.
+/// Returns [`PlanError::TypeMismatch`] if the mutator would change the type of a key,
+/// or tries to do math with something that isn't a number, and [`PlanError::DivisionByZero`]
+/// if it divides by zero.
+pub fn apply_mutator(data: &mut InternalData, mutator: &Mutator) -> Result<(), PlanError> {
+    let key = mutator.key();
+    let mut value = data.get(&key).copied();
+    apply_mutator_to_value(&mut value, mutator, |other| data.get(&other).copied())?;
+    if let Some(value) = value {
+        data.insert(key, value);
+    } else {
+        data.remove(&key);
+    }
+    Ok(())
+}
+
+/// Applies `mutator` to the current value of its key, which is `None` if the key is missing.
+/// `value_of` looks up the value of the other key of a [`Mutator::CopyFrom`] or
+/// [`Mutator::AddKey`]. Only setting or copying a value adds a missing key, everything else
+/// leaves it missing. Copying a missing key removes this one, and adding it changes nothing.
+pub(crate) fn apply_mutator_to_value(
+    value: &mut Option<Datum>,
+    mutator: &Mutator,
+    value_of: impl FnOnce(Key) -> Option<Datum>,
+) -> Result<(), PlanError> {
+    match mutator {
+        Mutator::Set(_, new_value) => set_value(value, *new_value),
+        Mutator::CopyFrom(_, other) => match value_of(*other) {
+            Some(new_value) => set_value(value, new_value),
+            None => {
+                *value = None;
+                Ok(())
             }
+        },
+        Mutator::Increment(_, by) => update_value(value, |current| current.try_add(*by)),
+        Mutator::Decrement(_, by) => update_value(value, |current| current.try_sub(*by)),
+        Mutator::Multiply(_, by) => update_value(value, |current| current.try_mul(*by)),
+        Mutator::Divide(_, by) => update_value(value, |current| current.try_div(*by)),
+        Mutator::Min(_, bound) => update_value(value, |current| current.try_min(*bound)),
+        Mutator::Max(_, bound) => update_value(value, |current| current.try_max(*bound)),
+        Mutator::Toggle(_) => update_value(value, Datum::try_toggle),
+        Mutator::AddKey(_, other) => match value_of(*other) {
+            Some(by) => update_value(value, |current| current.try_add(by)),
+            None => Ok(()),
+        },
+        Mutator::Remove(_) => {
+            *value = None;
+            Ok(())
         }
     }
+    .map_err(|err| err.for_key(mutator.key()))
+}
+
+/// Sets `value` to `new_value`, unless that would change its type
+fn set_value(value: &mut Option<Datum>, new_value: Datum) -> Result<(), PlanError> {
+    if let Some(current_value) = value
+        && !current_value.same_type(&new_value)
+    {
+        return Err(current_value.mismatch(&new_value));
+    }
+    *value = Some(new_value);
+    Ok(())
+}
+
+/// Replaces `value` with what `update` returns for it, if the key isn't missing
+fn update_value(
+    value: &mut Option<Datum>,
+    update: impl FnOnce(Datum) -> Result<Datum, PlanError>,
+) -> Result<(), PlanError> {
+    if let Some(current_value) = value {
+        *current_value = update(*current_value)?;
+    }
+    Ok(())
 }
 
 /// Formats a human-readable version of a list of [`Mutator`]s.
@@ -63,6 +204,30 @@ pub fn format_mutators(mutators: Vec<Mutator>) -> String {
             Mutator::Decrement(k, v) => {
                 output.push_str(&format!("\t\t{k} - {v}\n"));
             }
+            Mutator::Multiply(k, v) => {
+                output.push_str(&format!("\t\t{k} * {v}\n"));
+            }
+            Mutator::Divide(k, v) => {
+                output.push_str(&format!("\t\t{k} / {v}\n"));
+            }
+            Mutator::Min(k, v) => {
+                output.push_str(&format!("\t\t{k} = min({k}, {v})\n"));
+            }
+            Mutator::Max(k, v) => {
+                output.push_str(&format!("\t\t{k} = max({k}, {v})\n"));
+            }
+            Mutator::CopyFrom(k, other) => {
+                output.push_str(&format!("\t\t{k} = {other}\n"));
+            }
+            Mutator::Toggle(k) => {
+                output.push_str(&format!("\t\t{k} = !{k}\n"));
+            }
+            Mutator::AddKey(k, other) => {
+                output.push_str(&format!("\t\t{k} + {other}\n"));
+            }
+            Mutator::Remove(k) => {
+                output.push_str(&format!("\t\tremove {k}\n"));
+            }
         }
     }
     output
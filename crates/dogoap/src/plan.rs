@@ -0,0 +1,106 @@
+use std::fmt::{self, Display};
+
+use crate::{
+    cost::Cost, effect::Effect, error::BudgetLimit, goal::Goal, localstate::LocalState,
+    mutator::format_mutators,
+};
+
+/// The steps that lead from a start [`LocalState`] to a [`Goal`], as found by
+/// [`make_plan`](crate::planner::make_plan)
+///
+/// Each step is the [`Effect`] of one [`Action`](crate::action::Action), with its cost and the
+/// state after executing it.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
+pub struct Plan {
+    /// The state the plan starts from
+    pub start: LocalState,
+    /// The steps to execute, in order
+    pub steps: Vec<Effect>,
+    /// Total cost of the plan
+    pub cost: Cost,
+    /// The [`Goal`] the plan reaches
+    pub goal: Goal,
+    /// Set to the limit that stopped the search when the plan doesn't reach the [`Goal`], but only
+    /// gets as close to it as the search managed to, see
+    /// [`make_plan_anytime`](crate::planner::make_plan_anytime)
+    pub partial: Option<BudgetLimit>,
+}
+
+impl Plan {
+    /// Whether the plan stops short of the [`Goal`]
+    pub fn is_partial(&self) -> bool {
+        self.partial.is_some()
+    }
+
+    /// The number of steps in the plan
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Whether the plan has no steps
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Iterates over the steps of the plan, in order
+    pub fn iter(&self) -> std::slice::Iter<'_, Effect> {
+        self.steps.iter()
+    }
+
+    /// Iterates over the names of the [`Action`](crate::action::Action)s to execute, in order
+    pub fn actions(&self) -> impl Iterator<Item = &str> {
+        self.steps.iter().map(|step| step.action.as_str())
+    }
+
+    /// Iterates over the start state and the state after each step
+    pub fn states(&self) -> impl Iterator<Item = &LocalState> {
+        std::iter::once(&self.start).chain(self.steps.iter().map(|step| &step.state))
+    }
+
+    /// The state after executing every step
+    pub fn final_state(&self) -> &LocalState {
+        self.steps.last().map_or(&self.start, |step| &step.state)
+    }
+}
+
+impl IntoIterator for Plan {
+    type Item = Effect;
+    type IntoIter = std::vec::IntoIter<Effect>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.steps.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Plan {
+    type Item = &'a Effect;
+    type IntoIter = std::slice::Iter<'a, Effect>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.steps.iter()
+    }
+}
+
+/// Shows what [`Action`](crate::action::Action)s need to be executed and what the results of
+/// each Action is
+impl Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "\t\t= INITIAL STATE")?;
+        for (k, v) in &self.start.data {
+            writeln!(f, "\t\t{k} = {v}")?;
+        }
+        write!(f, "\n\t\t---\n")?;
+        for step in &self.steps {
+            writeln!(f, "\t\t= DO ACTION {:#?}", step.action)?;
+            writeln!(f, "\t\tMUTATES:")?;
+            write!(f, "{}", format_mutators(step.mutators.clone()))?;
+            write!(f, "\n\t\t---\n")?;
+        }
+        writeln!(f, "\t\t= FINAL STATE (COST: {})", self.cost)?;
+        for (k, v) in &self.final_state().data {
+            writeln!(f, "\t\t{k} = {v}")?;
+        }
+        Ok(())
+    }
+}
@@ -1,96 +1,215 @@
 //! Types related to planning.
 
+use std::sync::Arc;
+use std::time::Duration;
+
+pub use crate::domain::{CompiledDomain, PlanningContext};
+pub use crate::error::{SimulationError, StepFailure};
+pub use crate::incremental::IncrementalPlanner;
+pub use crate::plan::Plan;
+pub use crate::repair::{repair_plan, repair_plan_with_options};
+
 use crate::{
     action::Action,
-    compare::{check_preconditions, compare_values},
+    compare::compare_key,
+    cost::Cost,
     effect::Effect,
+    error::PlanError,
     goal::Goal,
+    heuristic::{DistanceHeuristic, Heuristic, HeuristicContext},
     localstate::LocalState,
-    mutator::{apply_mutator, format_mutators},
+    mutator::apply_mutator,
 };
 
-/// A Node holds things can return a state, used for path finding
-/// It's either the Initial [`LocalState`], or the [`LocalState`] after applying
-/// the [`Effect`]
-#[derive(Clone, Eq, PartialEq, Hash)]
-#[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
-pub enum Node {
-    /// The initial state of the planner
-    State(LocalState),
-    /// The state after applying an [`Effect`]
-    Effect(Effect),
-}
-
-impl Node {
-    /// Returns the state of the node
-    pub fn state(&self) -> &LocalState {
-        match self {
-            Node::Effect(effect) => &effect.state,
-            Node::State(state) => state,
+/// Rebuilds the [`Plan`] a search found, by applying the first [`Effect`] of each of the `steps`
+/// (indices into `actions`) to `start` in order
+pub(crate) fn replay_plan(
+    start: &LocalState,
+    actions: &[Action],
+    goal: &Goal,
+    steps: impl IntoIterator<Item = usize>,
+) -> Result<Plan, PlanError> {
+    let mut state = start.clone();
+    let mut plan_steps = Vec::new();
+    for index in steps {
+        let effect = &actions[index].effects[0];
+        let cost = effect.cost_in(&state);
+        for mutator in &effect.mutators {
+            apply_mutator(&mut state.data, mutator)?;
         }
+        plan_steps.push(Effect {
+            cost,
+            state: state.clone(),
+            ..effect.clone()
+        });
     }
+    Ok(Plan {
+        start: start.clone(),
+        cost: plan_steps.iter().map(|step| step.cost).sum(),
+        steps: plan_steps,
+        goal: goal.clone(),
+        partial: None,
+    })
 }
 
-impl std::fmt::Debug for Node {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Node::Effect(effect) => effect.fmt(f),
-            Node::State(state) => state.fmt(f),
+/// Executes the `plan` on a copy of `start`, step by step, and returns the state at the end
+///
+/// Before each step, the preconditions of the [`Action`] it was made from are checked, which is
+/// looked up in `actions` by name. Then the [`Mutator`](crate::mutator::Mutator)s of the step are
+/// applied. Use this to check whether a plan made earlier still works from the current state,
+/// without planning again.
+pub fn simulate_plan(
+    start: &LocalState,
+    plan: &[Effect],
+    actions: &[Action],
+) -> Result<LocalState, SimulationError> {
+    let mut state = start.clone();
+    for (step, effect) in plan.iter().enumerate() {
+        let fail = |reason| SimulationError { step, reason };
+        let action = actions
+            .iter()
+            .find(|action| action.key == effect.action)
+            .ok_or_else(|| fail(StepFailure::UnknownAction(effect.action.clone())))?;
+        for (key, comparison) in &action.preconditions {
+            let passed = compare_key(&state, *key, comparison)
+                .map_err(|err| fail(StepFailure::Error(err)))?;
+            if !passed {
+                return Err(fail(StepFailure::PreconditionFailed(
+                    *key,
+                    comparison.clone(),
+                )));
+            }
         }
-    }
-}
-
-fn heuristic(node: &Node, goal: &Goal) -> usize {
-    node.state().distance_to_goal(goal) as usize
-}
-
-fn successors<'a>(
-    node: &'a Node,
-    actions: &'a [Action],
-) -> impl Iterator<Item = (Node, usize)> + 'a {
-    let state = node.state();
-    actions.iter().filter_map(move |action| {
-        if !check_preconditions(state, action) || action.effects.is_empty() {
-            return None;
+        for condition in &action.conditions {
+            let holds = condition
+                .check(&state)
+                .map_err(|err| fail(StepFailure::Error(err)))?;
+            if !holds {
+                return Err(fail(StepFailure::ConditionFailed(condition.clone())));
+            }
         }
-        let new_state = state.clone();
-        let first_effect = &action.effects[0];
-
-        let mut new_data = new_state.data.clone();
-        for mutator in &first_effect.mutators {
-            apply_mutator(&mut new_data, mutator);
+        if let Some(precondition) = action
+            .precondition_fns
+            .iter()
+            .find(|precondition| !precondition.check(&state))
+        {
+            return Err(fail(StepFailure::PreconditionFnFailed(
+                precondition.name().to_string(),
+            )));
         }
-
-        let new_effect = Effect {
-            action: first_effect.action.clone(),
-            mutators: first_effect.mutators.clone(),
-            cost: first_effect.cost,
-            state: LocalState { data: new_data },
-        };
-        Some((Node::Effect(new_effect), first_effect.cost))
-    })
+        for mutator in &effect.mutators {
+            apply_mutator(&mut state.data, mutator).map_err(|err| fail(StepFailure::Error(err)))?;
+        }
+    }
+    Ok(state)
 }
 
-fn is_goal(node: &Node, goal: &Goal) -> bool {
-    goal.requirements.iter().all(|(key, value)| {
-        let state_val = node.state().data.get(key).unwrap_or_else(|| {
-            panic!(
-                "Couldn't find key {key:#?} in LocalState {:#?}",
-                node.state().data
-            )
-        });
-        compare_values(value, state_val)
-    })
+/// Estimates the cost of reaching `goal` from `state` with `actions`, like the planner does by
+/// default when searching with [`PlanningStrategy::StartToGoal`], see [`DistanceHeuristic`].
+/// Never more than the cost of the cheapest plan.
+pub fn estimate_remaining_cost(state: &LocalState, actions: &[Action], goal: &Goal) -> Cost {
+    DistanceHeuristic.estimate(state, &HeuristicContext::new(actions, goal))
 }
 
-/// Currently, only [`PlanningStrategy::StartToGoal`] is supported, which tries to find the chain of
-/// [`Effect`]s that lead to our [`Goal`] state
+/// Decides in which direction the planner searches for the chain of [`Effect`]s that lead
+/// to our [`Goal`] state
 #[derive(Default, Copy, Clone, Debug)]
+#[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
 pub enum PlanningStrategy {
     #[default]
     /// `StartToGoal` begins with our current state, and finds the most optimal path to the goal, based on the costs
     /// Might take longer time than `GoalToStart`, but finds the path with the lowest cost
     StartToGoal,
+    /// `GoalToStart` begins with the requirements of our [`Goal`], and works backwards through the
+    /// [`Action`]s that satisfy them until our current state fulfills what is left.
+    /// Only [`Action`]s that contribute to the remaining requirements are looked at, which makes it
+    /// a lot faster when you have many [`Action`]s that are irrelevant to the [`Goal`], but the plan
+    /// isn't guaranteed to be the cheapest one.
+    /// [`Condition`](crate::condition::Condition)s, [`Action::precondition_fns`],
+    /// [`Effect::cost_fn`](crate::effect::Effect::cost_fn),
+    /// [`Compare::ToKey`](crate::compare::Compare::ToKey) and mutators that scale, clamp or add
+    /// another key to a value, like [`Mutator::Multiply`](crate::mutator::Mutator::Multiply),
+    /// can't be regressed, so when the [`Goal`] or any [`Action`] has them, `StartToGoal` is used
+    /// instead.
+    GoalToStart,
+}
+
+/// Limits and settings for [`make_plan_with_options`]
+///
+/// All limits are off by default. When one of them stops the search, you get a
+/// [`PlanError::BudgetExceeded`] that says which one it was.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
+pub struct PlanOptions {
+    /// In which direction to search
+    pub strategy: PlanningStrategy,
+    /// Give up after expanding this many nodes
+    pub max_expanded_nodes: Option<usize>,
+    /// Only look for plans with at most this many [`Action`]s. The cheapest plan that fits is
+    /// found, so the search looks at a state again for every number of steps it's reached in.
+    pub max_plan_length: Option<usize>,
+    /// Only look for plans that cost at most this much in total
+    pub max_cost: Option<Cost>,
+    /// Give up after searching for this long. Uses [`std::time::Instant`], so don't set this on
+    /// platforms without a clock, like `wasm32-unknown-unknown`
+    pub timeout: Option<Duration>,
+    /// Guides [`PlanningStrategy::StartToGoal`] towards the [`Goal`]. Uses [`DistanceHeuristic`]
+    /// when not set.
+    #[cfg_attr(feature = "bevy_reflect", reflect(ignore))]
+    pub heuristic: Option<Arc<dyn Heuristic>>,
+    /// Picks randomly among the states that look equally good, and thereby among plans that cost
+    /// the same, instead of always going the same way. The same seed always picks the same plan.
+    /// Not used by [`IncrementalPlanner`].
+    pub seed: Option<u64>,
+}
+
+impl PlanOptions {
+    /// Creates new [`PlanOptions`] without any limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the [`PlanningStrategy`] to use
+    pub fn with_strategy(mut self, strategy: PlanningStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Limits how many nodes can be expanded
+    pub fn with_max_expanded_nodes(mut self, max: usize) -> Self {
+        self.max_expanded_nodes = Some(max);
+        self
+    }
+
+    /// Limits how many [`Action`]s the plan can have
+    pub fn with_max_plan_length(mut self, max: usize) -> Self {
+        self.max_plan_length = Some(max);
+        self
+    }
+
+    /// Limits the total cost of the plan
+    pub fn with_max_cost(mut self, max: impl Into<Cost>) -> Self {
+        self.max_cost = Some(max.into());
+        self
+    }
+
+    /// Limits how long the search can take
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the [`Heuristic`] to use
+    pub fn with_heuristic(mut self, heuristic: impl Heuristic + 'static) -> Self {
+        self.heuristic = Some(Arc::new(heuristic));
+        self
+    }
+
+    /// Sets the seed to pick among equally good plans with
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
 }
 
 /// Use [`make_plan`] instead
@@ -99,69 +218,91 @@ pub fn make_plan_with_strategy(
     start: &LocalState,
     actions: &[Action],
     goal: &Goal,
-) -> Option<(Vec<Node>, usize)> {
-    match strategy {
-        PlanningStrategy::StartToGoal => {
-            let start_node = Node::State(start.clone());
-            pathfinding::directed::astar::astar(
-                &start_node,
-                |node| successors(node, actions).collect::<Vec<_>>().into_iter(),
-                |node| heuristic(node, goal),
-                |node| is_goal(node, goal),
-            )
-        }
-    }
+) -> Result<Plan, PlanError> {
+    make_plan_with_options(
+        start,
+        actions,
+        goal,
+        &PlanOptions::new().with_strategy(strategy),
+    )
 }
 
-/// Returns a path of [`Node`]s that leads from our start [`LocalState`] to our
-/// [`Goal`] state
-pub fn make_plan(
+/// Like [`make_plan`], but stops searching once one of the limits in [`PlanOptions`] is hit
+pub fn make_plan_with_options(
     start: &LocalState,
     actions: &[Action],
     goal: &Goal,
-) -> Option<(Vec<Node>, usize)> {
+    options: &PlanOptions,
+) -> Result<Plan, PlanError> {
+    CompiledDomain::new(actions, goal).make_plan(start, options)
+}
+
+/// Like [`make_plan_with_options`], but when a limit is hit, returns the plan that leads to the
+/// state closest to the [`Goal`] instead of [`PlanError::BudgetExceeded`], going by
+/// [`PlanOptions::heuristic`]. Such a plan is marked with [`Plan::partial`].
+///
+/// You still get [`PlanError::BudgetExceeded`] if no state closer to the [`Goal`] than the start
+/// was found. Partial plans are only possible with [`PlanningStrategy::StartToGoal`], as the
+/// steps found by [`PlanningStrategy::GoalToStart`] can't be executed until the search reaches
+/// the start.
+pub fn make_plan_anytime(
+    start: &LocalState,
+    actions: &[Action],
+    goal: &Goal,
+    options: &PlanOptions,
+) -> Result<Plan, PlanError> {
+    CompiledDomain::new(actions, goal).make_plan_anytime(start, options)
+}
+
+/// Returns a [`Plan`] that leads from our start [`LocalState`] to our [`Goal`] state
+///
+/// Returns [`PlanError::GoalAlreadySatisfied`] if there is nothing to do, and
+/// [`PlanError::SearchExhausted`] if there is no way of reaching the [`Goal`].
+/// The search is unbounded, use [`make_plan_with_options`] to limit it.
+pub fn make_plan(start: &LocalState, actions: &[Action], goal: &Goal) -> Result<Plan, PlanError> {
     // Default to using Start -> Goal planning
-    make_plan_with_strategy(PlanningStrategy::StartToGoal, start, actions, goal)
+    make_plan_with_options(start, actions, goal, &PlanOptions::default())
+}
+
+/// Like [`make_plans`], but stops each search once one of the limits in [`PlanOptions`] is hit
+///
+/// Every plan after the first one takes its own searches, and the limits apply to each of them.
+/// [`PlanOptions::max_plan_length`] and [`PlanOptions::max_cost`] hold for every plan. Always
+/// searches from the start to the goal.
+pub fn make_plans_with_options(
+    start: &LocalState,
+    actions: &[Action],
+    goal: &Goal,
+    k: usize,
+    options: &PlanOptions,
+) -> Result<Vec<Plan>, PlanError> {
+    CompiledDomain::new(actions, goal).make_plans(start, options, k)
+}
+
+/// Returns up to `k` [`Plan`]s that lead from our start [`LocalState`] to our [`Goal`] state,
+/// cheapest first
+///
+/// Every plan has a different sequence of [`Action`]s, and none of them visits the same state
+/// twice. Fewer than `k` plans are returned when there aren't that many. Like [`make_plan`],
+/// returns [`PlanError::GoalAlreadySatisfied`] if there is nothing to do, and
+/// [`PlanError::SearchExhausted`] if there is no way of reaching the [`Goal`].
+pub fn make_plans(
+    start: &LocalState,
+    actions: &[Action],
+    goal: &Goal,
+    k: usize,
+) -> Result<Vec<Plan>, PlanError> {
+    make_plans_with_options(start, actions, goal, k, &PlanOptions::default())
 }
 
 /// Returns an iterator of all [`Effect`]s from a given plan
-pub fn get_effects_from_plan(plan: impl IntoIterator<Item = Node>) -> impl Iterator<Item = Effect> {
-    plan.into_iter().filter_map(|node| match node {
-        Node::Effect(effect) => Some(effect),
-        Node::State(_) => None,
-    })
+pub fn get_effects_from_plan(plan: Plan) -> impl Iterator<Item = Effect> {
+    plan.into_iter()
 }
 
 /// Formats a human-readable version of a plan from [`make_plan`] that shows
 /// what [`Action`]s needs to be executed and what the results of each Action is
 #[must_use]
-pub fn format_plan(plan: (Vec<Node>, usize)) -> String {
-    let mut output = String::new();
-    let nodes = plan.0;
-    let cost = plan.1;
-    let mut last_state = LocalState::new();
-    for node in nodes {
-        match node {
-            Node::Effect(effect) => {
-                output.push_str(&format!("\t\t= DO ACTION {:#?}\n", effect.action));
-                output.push_str("\t\tMUTATES:\n");
-                output.push_str(&format_mutators(effect.mutators));
-                last_state = effect.state.clone();
-            }
-            Node::State(s) => {
-                output.push_str("\t\t= INITIAL STATE\n");
-                for (k, v) in &s.data {
-                    output.push_str(&format!("\t\t{k} = {v}\n"));
-                }
-                last_state = s.clone();
-            }
-        }
-        output.push_str("\n\t\t---\n");
-    }
-    output.push_str(&format!("\t\t= FINAL STATE (COST: {cost})\n"));
-    for (k, v) in &last_state.data {
-        output.push_str(&format!("\t\t{k} = {v}\n"));
-    }
-
-    output
+pub fn format_plan(plan: Plan) -> String {
+    plan.to_string()
 }
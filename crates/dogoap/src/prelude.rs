@@ -1,10 +1,15 @@
 //! Everything you need to use dogoap
 
 pub use crate::action::Action;
-pub use crate::compare::Compare;
+pub use crate::compare::{Compare, PreconditionFn, Relation};
+pub use crate::condition::Condition;
+pub use crate::cost::{Cost, CostFn};
 pub use crate::datum::Datum;
 pub use crate::effect::Effect;
+pub use crate::error::{BudgetLimit, PlanError};
 pub use crate::goal::Goal;
+pub use crate::heuristic::Heuristic;
+pub use crate::key::Key;
 pub use crate::localstate::LocalState;
 pub use crate::mutator::Mutator;
-pub use crate::planner::{Node, format_plan, get_effects_from_plan, make_plan};
+pub use crate::planner::{Plan, format_plan, get_effects_from_plan, make_plan};
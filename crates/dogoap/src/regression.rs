@@ -0,0 +1,414 @@
+//! Backward chaining ("regressive") search, used by [`PlanningStrategy::GoalToStart`](crate::planner::PlanningStrategy::GoalToStart)
+//!
+//! Instead of applying [`Effect`]s to the start state until the [`Goal`] is reached, we start
+//! with the requirements of the [`Goal`] and regress them through the [`Action`]s that could
+//! have produced them. Every step replaces the requirements an [`Action`] satisfies with its
+//! preconditions, until the start [`LocalState`] satisfies everything that is left.
+//!
+//! Only [`Action`]s that actually contribute to an open requirement are considered, so actions
+//! unrelated to the goal never get expanded.
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use crate::{
+    action::Action,
+    compare::{Compare, compare_key, compare_values},
+    cost::Cost,
+    datum::Datum,
+    error::PlanError,
+    goal::Goal,
+    key::Key,
+    localstate::LocalState,
+    mutator::Mutator,
+    planner::{Plan, PlanOptions, replay_plan},
+    search::{SearchBuffers, SearchSpace, Successor, search},
+};
+
+/// The open requirements while regressing. A key can have multiple [`Compare`]s, as both the
+/// [`Goal`] and preconditions of [`Action`]s can put requirements on the same key.
+type Requirements = BTreeMap<Key, Vec<Compare>>;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct RegressionNode {
+    pub(crate) requirements: Requirements,
+}
+
+impl RegressionNode {
+    pub(crate) fn from_goal(goal: &Goal) -> Self {
+        let requirements = goal
+            .requirements
+            .iter()
+            .map(|(key, compare)| (*key, vec![compare.clone()]))
+            .collect();
+        Self { requirements }
+    }
+
+    /// Regresses the requirements through `action`, returning the requirements that need to hold
+    /// *before* the action is executed, and whether the action contributes to any of them.
+    /// Returns `None` if the action would break one of the requirements.
+    pub(crate) fn regress(&self, action: &Action) -> Result<Option<(Self, bool)>, PlanError> {
+        let Some(effect) = action.effects.first() else {
+            return Ok(None);
+        };
+        let mut requirements = self.requirements.clone();
+        let mut contributes = false;
+
+        // The mutators are applied in order when planning forwards, so we undo them in reverse
+        for mutator in effect.mutators.iter().rev() {
+            match mutator {
+                Mutator::Set(key, value) => {
+                    let Some(compares) = requirements.get(key) else {
+                        continue;
+                    };
+                    if !all_pass(compares, value).map_err(|err| err.for_key(*key))? {
+                        return Ok(None);
+                    }
+                    requirements.remove(key);
+                    contributes = true;
+                }
+                Mutator::Increment(key, value) | Mutator::Decrement(key, value) => {
+                    let Some(compares) = requirements.get_mut(key) else {
+                        continue;
+                    };
+                    let increment = matches!(mutator, Mutator::Increment(..));
+                    for compare in compares.iter_mut() {
+                        let shifted =
+                            shift(compare, value, increment).map_err(|err| err.for_key(*key))?;
+                        contributes |= moves_towards(compare, value, increment);
+                        *compare = shifted;
+                    }
+                }
+                Mutator::Toggle(key) => {
+                    let Some(compares) = requirements.get_mut(key) else {
+                        continue;
+                    };
+                    // Flipping the value turns `>` into `<`, so only comparisons that don't
+                    // depend on the order are flipped along with it
+                    if !compares.iter().all(|compare| {
+                        matches!(
+                            compare,
+                            Compare::Equals(_) | Compare::NotEquals(_) | Compare::OneOf(_)
+                        )
+                    }) {
+                        return Ok(None);
+                    }
+                    for compare in compares.iter_mut() {
+                        *compare = compare
+                            .try_map(Datum::try_toggle)
+                            .map_err(|err| err.for_key(*key))?;
+                    }
+                    contributes = true;
+                }
+                Mutator::CopyFrom(key, other) => {
+                    // What has to hold for the key afterwards has to hold for the other key before
+                    let Some(compares) = requirements.remove(key) else {
+                        continue;
+                    };
+                    requirements.entry(*other).or_default().extend(compares);
+                    contributes = true;
+                }
+                // A missing key can't meet any requirement, and the value before the rest can't
+                // be worked out, see `can_regress`
+                Mutator::Remove(key)
+                | Mutator::Multiply(key, _)
+                | Mutator::Divide(key, _)
+                | Mutator::Min(key, _)
+                | Mutator::Max(key, _)
+                | Mutator::AddKey(key, _) => {
+                    if requirements.contains_key(key) {
+                        return Ok(None);
+                    }
+                }
+            }
+        }
+
+        for (key, compare) in &action.preconditions {
+            requirements.entry(*key).or_default().push(compare.clone());
+        }
+
+        for (key, compares) in requirements.iter_mut() {
+            match normalize(std::mem::take(compares)).map_err(|err| err.for_key(*key))? {
+                Some(normalized) => *compares = normalized,
+                None => return Ok(None),
+            }
+        }
+
+        Ok(Some((Self { requirements }, contributes)))
+    }
+
+    pub(crate) fn is_satisfied_by(&self, state: &LocalState) -> Result<bool, PlanError> {
+        self.requirements
+            .iter()
+            .flat_map(|(key, compares)| compares.iter().map(move |compare| (key, compare)))
+            .try_fold(true, |satisfied, (key, compare)| {
+                Ok(compare_key(state, *key, compare)? && satisfied)
+            })
+    }
+
+    /// How many of the open requirements the start state doesn't meet yet. Compared to the
+    /// distance between [`Datum`]s this stays in the same scale as the cost of [`Action`]s, as
+    /// every unmet requirement needs at least one more step to be resolved
+    fn unmet_requirements(&self, state: &LocalState) -> usize {
+        self.requirements
+            .iter()
+            .flat_map(|(key, compares)| compares.iter().map(move |compare| (key, compare)))
+            .filter(|(key, compare)| compare_key(state, **key, compare) != Ok(true))
+            .count()
+    }
+
+    /// How many keys have a requirement the start state doesn't meet yet. An [`Action`] can
+    /// only meet the requirements on the keys it changes, so unlike
+    /// [`RegressionNode::unmet_requirements`] this drops by at most one for each of its
+    /// mutators.
+    pub(crate) fn unmet_keys(&self, state: &LocalState) -> usize {
+        self.requirements
+            .iter()
+            .filter(|(key, compares)| {
+                compares
+                    .iter()
+                    .any(|compare| compare_key(state, **key, compare) != Ok(true))
+            })
+            .count()
+    }
+}
+
+/// Whether what has to hold before `mutator` can be worked out from what has to hold after it.
+/// Scaling or clamping a value, or adding another key to it, can be reached from too many values
+/// to put into [`Compare`]s, so [`Action`]s that do that can't be regressed.
+pub(crate) fn can_regress(mutator: &Mutator) -> bool {
+    !matches!(
+        mutator,
+        Mutator::Multiply(..)
+            | Mutator::Divide(..)
+            | Mutator::Min(..)
+            | Mutator::Max(..)
+            | Mutator::AddKey(..)
+    )
+}
+
+fn all_pass(compares: &[Compare], value: &Datum) -> Result<bool, PlanError> {
+    compares.iter().try_fold(true, |passed, compare| {
+        Ok(compare_values(compare, value)? && passed)
+    })
+}
+
+/// Whether incrementing (or decrementing) by `value` helps to satisfy `compare`
+fn moves_towards(compare: &Compare, value: &Datum, increment: bool) -> bool {
+    let zero = match value {
+        Datum::I64(_) => Datum::I64(0),
+        Datum::F64(_) => Datum::F64(0.0),
+        _ => return false,
+    };
+    let positive = if increment {
+        *value > zero
+    } else {
+        *value < zero
+    };
+    match compare {
+        Compare::Equals(_) | Compare::NotEquals(_) | Compare::InRange(..) | Compare::OneOf(_) => {
+            *value != zero
+        }
+        Compare::GreaterThanEquals(_) | Compare::GreaterThan(_) => positive,
+        Compare::LessThanEquals(_) | Compare::LessThan(_) => !positive && *value != zero,
+        // Depends on the value of the other key, which isn't known while regressing
+        Compare::ToKey(..) => false,
+    }
+}
+
+/// Moves the value of `compare` so it applies to the value before incrementing
+/// (or decrementing) by `value`
+fn shift(compare: &Compare, value: &Datum, increment: bool) -> Result<Compare, PlanError> {
+    compare.try_map(|bound| {
+        if increment {
+            bound.try_sub(*value)
+        } else {
+            bound.try_add(*value)
+        }
+    })
+}
+
+/// Merges all the [`Compare`]s for one key into their tightest form, so equivalent
+/// [`RegressionNode`]s are also equal. Returns `None` if no value could satisfy them all.
+/// [`Compare::ToKey`]s can't be merged without the value of the other key, so they are kept as
+/// they are.
+fn normalize(compares: Vec<Compare>) -> Result<Option<Vec<Compare>>, PlanError> {
+    let (to_keys, compares): (Vec<Compare>, Vec<Compare>) = compares
+        .into_iter()
+        .partition(|compare| compare.other_key().is_some());
+    Ok(normalize_values(compares)?.map(|mut normalized| {
+        normalized.extend(to_keys);
+        normalized
+    }))
+}
+
+fn normalize_values(compares: Vec<Compare>) -> Result<Option<Vec<Compare>>, PlanError> {
+    let mut equals: Option<Datum> = None;
+    let mut one_of: Option<&Vec<Datum>> = None;
+    // The bounds, and whether they are strict
+    let mut lower: Option<(Datum, bool)> = None;
+    let mut upper: Option<(Datum, bool)> = None;
+    let mut not_equals: Vec<Datum> = vec![];
+
+    for compare in &compares {
+        match compare {
+            Compare::Equals(v) => match equals {
+                Some(existing) if existing != *v => return Ok(None),
+                _ => equals = Some(*v),
+            },
+            Compare::NotEquals(v) => not_equals.push(*v),
+            Compare::GreaterThanEquals(v) => tighten(&mut lower, *v, false, Ordering::Greater),
+            Compare::GreaterThan(v) => tighten(&mut lower, *v, true, Ordering::Greater),
+            Compare::LessThanEquals(v) => tighten(&mut upper, *v, false, Ordering::Less),
+            Compare::LessThan(v) => tighten(&mut upper, *v, true, Ordering::Less),
+            Compare::InRange(min, max) => {
+                tighten(&mut lower, *min, false, Ordering::Greater);
+                tighten(&mut upper, *max, false, Ordering::Less);
+            }
+            Compare::OneOf(values) => one_of = one_of.or(Some(values)),
+            // Split off by `normalize`
+            Compare::ToKey(..) => {}
+        }
+    }
+
+    if let Some(value) = equals {
+        return Ok(all_pass(&compares, &value)?.then(|| vec![Compare::Equals(value)]));
+    }
+
+    if let Some(values) = one_of {
+        // Only the values that pass everything else are left to pick from
+        let mut left = vec![];
+        for value in values {
+            if all_pass(&compares, value)? {
+                left.push(*value);
+            }
+        }
+        left.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        left.dedup();
+        return Ok(match left.as_slice() {
+            [] => None,
+            [value] => Some(vec![Compare::Equals(*value)]),
+            _ => Some(vec![Compare::OneOf(left)]),
+        });
+    }
+
+    if let (Some((min, min_strict)), Some((max, max_strict))) = (lower, upper)
+        && (min > max || (min == max && (min_strict || max_strict)))
+    {
+        return Ok(None);
+    }
+
+    not_equals.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    not_equals.dedup();
+
+    Ok(Some(
+        lower
+            .map(|(v, strict)| {
+                if strict {
+                    Compare::GreaterThan(v)
+                } else {
+                    Compare::GreaterThanEquals(v)
+                }
+            })
+            .into_iter()
+            .chain(upper.map(|(v, strict)| {
+                if strict {
+                    Compare::LessThan(v)
+                } else {
+                    Compare::LessThanEquals(v)
+                }
+            }))
+            .chain(not_equals.into_iter().map(Compare::NotEquals))
+            .collect(),
+    ))
+}
+
+/// Replaces `bound` with `value` if it's tighter, which is when it's further in the direction
+/// of `tighter`, or the same but strict
+fn tighten(bound: &mut Option<(Datum, bool)>, value: Datum, strict: bool, tighter: Ordering) {
+    let replace =
+        bound.is_none_or(
+            |(existing, existing_strict)| match value.partial_cmp(&existing) {
+                Some(Ordering::Equal) => strict && !existing_strict,
+                ordering => ordering == Some(tighter),
+            },
+        );
+    if replace {
+        *bound = Some((value, strict));
+    }
+}
+
+/// Searches backwards from the requirements of the [`Goal`] until `start` satisfies them
+struct RegressionSearch<'a> {
+    start: &'a LocalState,
+    actions: &'a [Action],
+    /// What the cheapest of the `actions` costs
+    cheapest: Cost,
+}
+
+impl SearchSpace for RegressionSearch<'_> {
+    type Node = RegressionNode;
+
+    fn successors(
+        &self,
+        node: &RegressionNode,
+        successors: &mut Vec<Successor<RegressionNode>>,
+    ) -> Result<(), PlanError> {
+        for (index, action) in self.actions.iter().enumerate() {
+            let Some(effect) = action.effects.first() else {
+                continue;
+            };
+            if let Some((regressed, true)) = node.regress(action)? {
+                successors.push(Successor {
+                    node: regressed,
+                    action: index,
+                    cost: effect.cost,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn heuristic(&self, node: &RegressionNode) -> Cost {
+        // Every unmet requirement needs at least one more step, which costs at least as much as
+        // the cheapest action
+        self.cheapest * node.unmet_requirements(self.start) as f64
+    }
+
+    fn is_goal(&self, node: &RegressionNode) -> Result<bool, PlanError> {
+        node.is_satisfied_by(self.start)
+    }
+}
+
+/// Finds a plan by regressing from the [`Goal`] towards `start`, and returns it in the same shape
+/// as the forward search does, so the [`Effect`]s carry the states from `start` onwards.
+pub(crate) fn make_plan_regressive(
+    start: &LocalState,
+    actions: &[Action],
+    goal: &Goal,
+    options: &PlanOptions,
+    buffers: &mut SearchBuffers<RegressionNode>,
+) -> Result<Plan, PlanError> {
+    let cheapest = actions
+        .iter()
+        .filter_map(|action| action.effects.first())
+        .map(|effect| effect.cost)
+        .min()
+        .unwrap_or(Cost::ZERO);
+    let space = RegressionSearch {
+        start,
+        actions,
+        cheapest,
+    };
+    let path = search(
+        &space,
+        RegressionNode::from_goal(goal),
+        options,
+        false,
+        buffers,
+    )?;
+
+    // The path goes from the goal towards the start, so the first step we regressed through is
+    // the last action we need to execute.
+    replay_plan(start, actions, goal, path.actions.into_iter().rev())
+}
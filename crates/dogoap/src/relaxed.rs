@@ -0,0 +1,632 @@
+//! The relaxed planning graph behind [`HMaxHeuristic`](crate::heuristic::HMaxHeuristic),
+//! [`HAddHeuristic`](crate::heuristic::HAddHeuristic) and
+//! [`FfHeuristic`](crate::heuristic::FfHeuristic)
+//!
+//! The relaxation ignores that [`Mutator`]s take values away: every value a key had stays
+//! available, so once a condition is met it stays met. Incrementing a key can then reach any value
+//! between where it started and how far the increments take it. This makes it cheap enough to
+//! estimate the cost of every precondition and requirement for each state the planner looks at.
+
+use std::cell::{RefCell, RefMut};
+
+use crate::{
+    action::Action,
+    compare::{Compare, compare_values},
+    cost::Cost,
+    datum::Datum,
+    goal::Goal,
+    key::Key,
+    localstate::LocalState,
+    mutator::Mutator,
+};
+
+/// The cost of conditions that can't be met at all
+pub(crate) const UNREACHABLE: Cost = Cost::INFINITY;
+
+/// How the costs of several conditions that are all needed add up
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Combine {
+    /// Only the most expensive one counts, which never overestimates
+    Max,
+    /// They are summed, which is closer to the real cost but can overestimate
+    Add,
+}
+
+impl Combine {
+    fn join(self, total: Cost, cost: Cost) -> Cost {
+        match self {
+            Combine::Max => total.max(cost),
+            Combine::Add => total + cost,
+        }
+    }
+}
+
+/// A requirement of the [`Goal`] or a precondition of an [`Action`]
+#[derive(Debug)]
+struct Condition {
+    key: Key,
+    compare: Compare,
+    /// The numbers that pass `compare`, if it compares numbers
+    target: Option<Target>,
+    /// The actions that set the key, and the value they set it to
+    sets: Vec<(usize, Datum)>,
+    /// The actions that increment or decrement the key, and by how much
+    steps: Vec<(usize, f64)>,
+    /// The actions that change the key a [`Compare::ToKey`] compares against
+    others: Vec<usize>,
+    /// The actions that change the key to a value that isn't known up front, like multiplying
+    /// it, which might meet the condition at once
+    changes: Vec<usize>,
+}
+
+/// The numbers that pass a [`Compare`]
+#[derive(Debug)]
+enum Target {
+    /// Every number from the first to the second, including both
+    Range(f64, f64),
+    /// Every number but this one
+    Not(f64),
+    /// Only these numbers
+    OneOf(Vec<f64>),
+}
+
+/// A [`Condition`](crate::condition::Condition), with each comparison replaced by the index of
+/// its [`Condition`]
+#[derive(Debug)]
+enum Expr {
+    Leaf(usize),
+    All(Vec<Expr>),
+    Any(Vec<Expr>),
+}
+
+#[derive(Debug)]
+struct RelaxedAction {
+    cost: Cost,
+    /// The conditions that need to hold before the action can be executed
+    preconditions: Vec<usize>,
+    /// The [`Action::conditions`] that need to hold as well
+    exprs: Vec<Expr>,
+}
+
+/// How a condition was met the cheapest, to find the actions of the relaxed plan
+#[derive(Clone, Copy, Debug)]
+struct Support {
+    action: usize,
+    times: usize,
+    /// The action that set the value the increments started from
+    base: Option<usize>,
+}
+
+/// The [`Action`]s and [`Goal`] in terms of the conditions they need and meet
+#[derive(Debug)]
+pub(crate) struct RelaxedDomain {
+    conditions: Vec<Condition>,
+    goal: Vec<usize>,
+    /// The [`Goal::conditions`] that need to hold as well
+    goal_exprs: Vec<Expr>,
+    actions: Vec<RelaxedAction>,
+    /// Reused for every state, as the heuristic runs for each one the planner looks at
+    scratch: RefCell<RelaxedCosts>,
+}
+
+/// The cost of meeting every condition from one state, and the buffers to work them out
+#[derive(Debug, Default)]
+pub(crate) struct RelaxedCosts {
+    costs: Vec<Cost>,
+    supports: Vec<Option<Support>>,
+    /// The value of each condition's key, if it's a number
+    current: Vec<Option<f64>>,
+    /// What it costs before each action can be executed
+    ready: Vec<Cost>,
+    /// Whether `ready` changed in the last round, as conditions only need another look then
+    updated: Vec<bool>,
+    /// How often each action is in the relaxed plan
+    times: Vec<usize>,
+    visited: Vec<bool>,
+    open: Vec<usize>,
+}
+
+fn as_number(datum: &Datum) -> Option<f64> {
+    match datum {
+        Datum::I64(value) => Some(*value as f64),
+        Datum::F64(value) => Some(*value),
+        _ => None,
+    }
+}
+
+impl Target {
+    fn new(compare: &Compare) -> Option<Self> {
+        // The closest numbers past a strict bound
+        let above = |value: &Datum| match value {
+            Datum::I64(value) => Some(*value as f64 + 1.0),
+            Datum::F64(value) => Some(value.next_up()),
+            _ => None,
+        };
+        let below = |value: &Datum| match value {
+            Datum::I64(value) => Some(*value as f64 - 1.0),
+            Datum::F64(value) => Some(value.next_down()),
+            _ => None,
+        };
+        Some(match compare {
+            Compare::Equals(value) => {
+                let value = as_number(value)?;
+                Target::Range(value, value)
+            }
+            Compare::NotEquals(value) => Target::Not(as_number(value)?),
+            Compare::GreaterThanEquals(value) => Target::Range(as_number(value)?, f64::INFINITY),
+            Compare::LessThanEquals(value) => Target::Range(f64::NEG_INFINITY, as_number(value)?),
+            Compare::GreaterThan(value) => Target::Range(above(value)?, f64::INFINITY),
+            Compare::LessThan(value) => Target::Range(f64::NEG_INFINITY, below(value)?),
+            Compare::InRange(min, max) => Target::Range(as_number(min)?, as_number(max)?),
+            Compare::OneOf(values) => {
+                Target::OneOf(values.iter().map(as_number).collect::<Option<_>>()?)
+            }
+            // Depends on the value of the other key
+            Compare::ToKey(..) => return None,
+        })
+    }
+
+    /// The changes to `value` that would each make it pass
+    fn gaps(&self, value: f64) -> impl Iterator<Item = f64> + '_ {
+        let (gap, points): (Option<f64>, &[f64]) = match self {
+            Target::Range(min, _) if value < *min => (Some(min - value), &[]),
+            Target::Range(_, max) if value > *max => (Some(max - value), &[]),
+            // Any change at all will do
+            Target::Not(target) if value == *target => (Some(f64::MIN_POSITIVE), &[]),
+            Target::Range(..) | Target::Not(_) => (Some(0.0), &[]),
+            Target::OneOf(points) => (None, points),
+        };
+        gap.into_iter()
+            .chain(points.iter().map(move |point| point - value))
+    }
+}
+
+impl Condition {
+    /// The changes to `value` that would each make it pass, none if it doesn't compare numbers
+    fn gaps(&self, value: f64) -> impl Iterator<Item = f64> + '_ {
+        self.target
+            .iter()
+            .flat_map(move |target| target.gaps(value))
+    }
+
+    /// How many changes by `step` it takes to close `gap`, if they ever do.
+    /// Overshooting is fine in the relaxation.
+    fn times_needed(&self, gap: f64, step: f64) -> Option<usize> {
+        if gap == 0.0 {
+            return Some(0);
+        }
+        if matches!(self.target, Some(Target::Not(_))) {
+            return Some(1);
+        }
+        (gap.signum() == step.signum()).then(|| (gap / step).ceil() as usize)
+    }
+}
+
+impl RelaxedDomain {
+    pub(crate) fn new(actions: &[Action], goal: &Goal) -> Self {
+        let mut conditions: Vec<Condition> = vec![];
+        let mut index_of = |key: Key, compare: &Compare| {
+            if let Some(index) = conditions
+                .iter()
+                .position(|condition| condition.key == key && condition.compare == *compare)
+            {
+                return index;
+            }
+            conditions.push(Condition {
+                key,
+                compare: compare.clone(),
+                target: Target::new(compare),
+                sets: vec![],
+                steps: vec![],
+                others: vec![],
+                changes: vec![],
+            });
+            conditions.len() - 1
+        };
+
+        let goal_conditions = goal
+            .requirements
+            .iter()
+            .map(|(key, compare)| index_of(*key, compare))
+            .collect();
+        let goal_exprs = Self::exprs(&goal.conditions, &mut index_of);
+        let relaxed_actions = actions
+            .iter()
+            .map(|action| RelaxedAction {
+                cost: action
+                    .effects
+                    .first()
+                    .map_or(Cost::ZERO, |effect| effect.cost),
+                preconditions: action
+                    .preconditions
+                    .iter()
+                    .map(|(key, compare)| index_of(*key, compare))
+                    .collect(),
+                exprs: Self::exprs(&action.conditions, &mut index_of),
+            })
+            .collect();
+
+        for (index, action) in actions.iter().enumerate() {
+            let Some(effect) = action.effects.first() else {
+                continue;
+            };
+            for mutator in &effect.mutators {
+                for condition in conditions.iter_mut() {
+                    if condition.compare.other_key() == Some(mutator.key()) {
+                        condition.others.push(index);
+                    }
+                    match mutator {
+                        Mutator::Set(key, value) if *key == condition.key => {
+                            condition.sets.push((index, *value));
+                        }
+                        Mutator::Increment(key, value) if *key == condition.key => {
+                            if let Some(step) = as_number(value).filter(|step| *step != 0.0) {
+                                condition.steps.push((index, step));
+                            }
+                        }
+                        Mutator::Decrement(key, value) if *key == condition.key => {
+                            if let Some(step) = as_number(value).filter(|step| *step != 0.0) {
+                                condition.steps.push((index, -step));
+                            }
+                        }
+                        // Removing the key can't meet anything
+                        Mutator::Remove(_) => {}
+                        mutator if mutator.key() == condition.key => {
+                            condition.changes.push(index);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        Self {
+            conditions,
+            goal: goal_conditions,
+            goal_exprs,
+            actions: relaxed_actions,
+            scratch: RefCell::default(),
+        }
+    }
+
+    /// Negated comparisons can hold on the boundary, which only makes the estimate lower
+    fn exprs(
+        conditions: &[crate::condition::Condition],
+        index_of: &mut impl FnMut(Key, &Compare) -> usize,
+    ) -> Vec<Expr> {
+        conditions
+            .iter()
+            .map(|condition| {
+                condition.fold(
+                    &mut |key, compare| Expr::Leaf(index_of(key, compare)),
+                    &mut Expr::All,
+                    &mut Expr::Any,
+                )
+            })
+            .collect()
+    }
+
+    fn combine(combine: Combine, costs: &[Cost], conditions: &[usize]) -> Cost {
+        let mut total = Cost::ZERO;
+        for &condition in conditions {
+            let cost = costs[condition];
+            if cost == UNREACHABLE {
+                return UNREACHABLE;
+            }
+            total = combine.join(total, cost);
+        }
+        total
+    }
+
+    fn expr_cost(combine: Combine, costs: &[Cost], expr: &Expr) -> Cost {
+        match expr {
+            Expr::Leaf(condition) => costs[*condition],
+            Expr::All(exprs) => exprs
+                .iter()
+                .map(|expr| Self::expr_cost(combine, costs, expr))
+                .fold(Cost::ZERO, |total, cost| combine.join(total, cost)),
+            // Only the cheapest way to meet it counts
+            Expr::Any(exprs) => exprs
+                .iter()
+                .map(|expr| Self::expr_cost(combine, costs, expr))
+                .min()
+                .unwrap_or(UNREACHABLE),
+        }
+    }
+
+    /// The cost of meeting all of `conditions` and `exprs`
+    fn requirement_cost(
+        combine: Combine,
+        costs: &[Cost],
+        conditions: &[usize],
+        exprs: &[Expr],
+    ) -> Cost {
+        exprs
+            .iter()
+            .map(|expr| Self::expr_cost(combine, costs, expr))
+            .fold(Self::combine(combine, costs, conditions), |total, cost| {
+                combine.join(total, cost)
+            })
+    }
+
+    /// Adds the conditions `expr` needs to `open`, following the cheapest branch of each `Any`
+    fn push_leaves(costs: &[Cost], expr: &Expr, open: &mut Vec<usize>) {
+        match expr {
+            Expr::Leaf(condition) => open.push(*condition),
+            Expr::All(exprs) => {
+                for expr in exprs {
+                    Self::push_leaves(costs, expr, open);
+                }
+            }
+            Expr::Any(exprs) => {
+                if let Some(cheapest) = exprs
+                    .iter()
+                    .min_by_key(|expr| Self::expr_cost(Combine::Add, costs, expr))
+                {
+                    Self::push_leaves(costs, cheapest, open);
+                }
+            }
+        }
+    }
+
+    /// Propagates the cost of meeting every condition from `state`, until nothing gets cheaper
+    pub(crate) fn costs(&self, state: &LocalState, combine: Combine) -> RefMut<'_, RelaxedCosts> {
+        let mut scratch = self.scratch.borrow_mut();
+        let RelaxedCosts {
+            costs,
+            supports,
+            current,
+            ready,
+            updated,
+            ..
+        } = &mut *scratch;
+        costs.clear();
+        current.clear();
+        for condition in &self.conditions {
+            let value = state.data.get(&condition.key);
+            costs.push(match value {
+                Some(value)
+                    if condition
+                        .compare
+                        .resolve(state)
+                        .is_ok_and(|compare| compare_values(&compare, value) == Ok(true)) =>
+                {
+                    Cost::ZERO
+                }
+                _ => UNREACHABLE,
+            });
+            current.push(value.and_then(as_number));
+        }
+        supports.clear();
+        supports.resize(costs.len(), None);
+        ready.clear();
+        ready.resize(self.actions.len(), UNREACHABLE);
+        updated.clear();
+        updated.resize(self.actions.len(), false);
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (index, action) in self.actions.iter().enumerate() {
+                let cost =
+                    Self::requirement_cost(combine, costs, &action.preconditions, &action.exprs);
+                updated[index] = cost != ready[index];
+                ready[index] = cost;
+            }
+
+            for (index, condition) in self.conditions.iter().enumerate() {
+                let affected = condition
+                    .sets
+                    .iter()
+                    .map(|(action, _)| action)
+                    .chain(condition.steps.iter().map(|(action, _)| action))
+                    .chain(&condition.others)
+                    .chain(&condition.changes)
+                    .any(|&action| updated[action]);
+                if costs[index] == Cost::ZERO || !affected {
+                    continue;
+                }
+                if let Some((cost, support)) =
+                    self.cheapest_support(condition, current[index], ready, combine)
+                    && cost < costs[index]
+                {
+                    costs[index] = cost;
+                    supports[index] = Some(support);
+                    changed = true;
+                }
+            }
+        }
+
+        scratch
+    }
+
+    /// The cheapest way to meet `condition`, given what it costs before each action can be
+    /// executed, and the `current` value of its key
+    fn cheapest_support(
+        &self,
+        condition: &Condition,
+        current: Option<f64>,
+        ready: &[Cost],
+        combine: Combine,
+    ) -> Option<(Cost, Support)> {
+        let executed = |action: usize| ready[action] + self.actions[action].cost;
+        let mut best: Option<(Cost, Support)> = None;
+        let mut offer = |cost: Cost, support: Support| {
+            if best.is_none_or(|(best, _)| cost < best) {
+                best = Some((cost, support));
+            }
+        };
+
+        if condition.compare.other_key().is_some() {
+            // Either side can change, possibly all the way at once, so any action that changes
+            // one of them might be all it takes
+            let actions = condition.sets.iter().map(|(action, _)| action);
+            let actions = actions
+                .chain(condition.steps.iter().map(|(action, _)| action))
+                .chain(&condition.others)
+                .chain(&condition.changes);
+            for &action in actions {
+                if ready[action] != UNREACHABLE {
+                    let support = Support {
+                        action,
+                        times: 1,
+                        base: None,
+                    };
+                    offer(executed(action), support);
+                }
+            }
+            return best;
+        }
+
+        let sets = condition
+            .sets
+            .iter()
+            .filter(|(_, value)| compare_values(&condition.compare, value) == Ok(true))
+            .map(|(action, _)| action);
+        for &action in sets.chain(&condition.changes) {
+            if ready[action] != UNREACHABLE {
+                let support = Support {
+                    action,
+                    times: 1,
+                    base: None,
+                };
+                offer(executed(action), support);
+            }
+        }
+        if condition.steps.is_empty() {
+            return best;
+        }
+
+        // Increments can start from the current value, or from a value an action sets
+        let current = current.map(|value| (value, Cost::ZERO, None));
+        let set = condition.sets.iter().filter_map(|&(action, value)| {
+            let value = as_number(&value)?;
+            (ready[action] != UNREACHABLE).then(|| (value, executed(action), Some(action)))
+        });
+        for (value, base_cost, base) in current.into_iter().chain(set) {
+            for gap in condition.gaps(value) {
+                match combine {
+                    Combine::Max => {
+                        if let Some((ready_cost, steps_cost, support)) =
+                            self.steps_lower_bound(condition, gap, ready)
+                        {
+                            let support = Support { base, ..support };
+                            offer(base_cost.max(ready_cost) + steps_cost, support);
+                        }
+                    }
+                    Combine::Add => {
+                        for &(action, step) in &condition.steps {
+                            let Some(times) = condition.times_needed(gap, step) else {
+                                continue;
+                            };
+                            if times == 0 || ready[action] == UNREACHABLE {
+                                continue;
+                            }
+                            let cost = base_cost
+                                + ready[action]
+                                + self.actions[action].cost * times as f64;
+                            let support = Support {
+                                action,
+                                times,
+                                base,
+                            };
+                            offer(cost, support);
+                        }
+                    }
+                }
+            }
+        }
+        best
+    }
+
+    /// A lower bound for closing `gap` in `condition` by stepping, as the cost before the first
+    /// step can be taken, and the cost of the steps. The steps can mix different actions, so they
+    /// cost at least the gap times the best cost per unit of change of any action.
+    fn steps_lower_bound(
+        &self,
+        condition: &Condition,
+        gap: f64,
+        ready: &[Cost],
+    ) -> Option<(Cost, Cost, Support)> {
+        let mut ready_cost = UNREACHABLE;
+        let mut step_cost = UNREACHABLE;
+        let mut cost_per_unit = f64::INFINITY;
+        let mut support = None;
+        for &(action, step) in &condition.steps {
+            let Some(times) = condition.times_needed(gap, step) else {
+                continue;
+            };
+            if times == 0 || ready[action] == UNREACHABLE {
+                continue;
+            }
+            let cost = self.actions[action].cost;
+            ready_cost = ready_cost.min(ready[action]);
+            step_cost = step_cost.min(cost);
+            let per_unit = cost.value() / step.abs();
+            if per_unit < cost_per_unit {
+                cost_per_unit = per_unit;
+                support = Some(Support {
+                    action,
+                    times,
+                    base: None,
+                });
+            }
+        }
+        let support = support?;
+        let gap_cost = Cost::new(gap.abs() * cost_per_unit);
+        Some((ready_cost, step_cost.max(gap_cost), support))
+    }
+
+    /// The cost of meeting every requirement of the [`Goal`]
+    pub(crate) fn goal_cost(&self, costs: &RelaxedCosts, combine: Combine) -> Cost {
+        Self::requirement_cost(combine, &costs.costs, &self.goal, &self.goal_exprs)
+    }
+
+    /// The cost of the actions it takes to meet the [`Goal`] in the relaxation, following how each
+    /// condition was met the cheapest. Actions needed by several conditions only count once.
+    pub(crate) fn relaxed_plan_cost(&self, costs: &mut RelaxedCosts) -> Cost {
+        if self.goal_cost(costs, Combine::Max) == UNREACHABLE {
+            return UNREACHABLE;
+        }
+        let RelaxedCosts {
+            costs,
+            supports,
+            times,
+            visited,
+            open,
+            ..
+        } = costs;
+        times.clear();
+        times.resize(self.actions.len(), 0);
+        visited.clear();
+        visited.resize(self.conditions.len(), false);
+        open.clear();
+        open.extend(&self.goal);
+        for expr in &self.goal_exprs {
+            Self::push_leaves(costs, expr, open);
+        }
+        while let Some(condition) = open.pop() {
+            if visited[condition] || costs[condition] == Cost::ZERO {
+                continue;
+            }
+            visited[condition] = true;
+            let support = supports[condition]
+                .expect("Every reachable condition that isn't met yet has a support");
+            for (action, count) in [(support.action, support.times)]
+                .into_iter()
+                .chain(support.base.map(|base| (base, 1)))
+            {
+                times[action] = times[action].max(count);
+                open.extend(&self.actions[action].preconditions);
+                for expr in &self.actions[action].exprs {
+                    Self::push_leaves(costs, expr, open);
+                }
+            }
+        }
+        times
+            .iter()
+            .zip(&self.actions)
+            .map(|(&times, action)| action.cost * times as f64)
+            .sum()
+    }
+}
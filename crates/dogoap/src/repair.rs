@@ -0,0 +1,297 @@
+//! Repairing a plan after the state changed, keeping as much of it as possible
+//!
+//! The [`Goal`] is regressed back through the steps of the plan, which gives what needs to hold
+//! before each step for the rest of the plan to still reach the [`Goal`]. Then we search for the
+//! cheapest way from the current state to any of those, preferring the ones that keep the most
+//! of the plan.
+
+use std::cell::RefCell;
+
+use crate::{
+    action::Action,
+    compare::Compare,
+    cost::Cost,
+    datum::Datum,
+    domain::{CompiledDomain, PlanningContext, State},
+    effect::Effect,
+    error::{BudgetLimit, PlanError},
+    goal::Goal,
+    heuristic::{DistanceHeuristic, Heuristic, HeuristicContext},
+    localstate::LocalState,
+    planner::{Plan, PlanOptions, replay_plan},
+    regression::{RegressionNode, can_regress},
+    search::{SearchSpace, Successor, search},
+};
+
+/// The rest of the plan from one of its steps on
+struct Target {
+    /// Index of the first step of the plan that is kept
+    step: usize,
+    /// What needs to hold before that step for the rest of the plan to reach the [`Goal`]
+    requirements: Vec<(usize, Compare)>,
+    /// Whether nothing of the plan is kept, so the [`Goal`] itself needs to be reached, including
+    /// its [`Goal::conditions`]
+    is_goal: bool,
+}
+
+impl Target {
+    /// Returns `None` if one of the requirements is on a key the domain has no slot for
+    fn new(step: usize, node: &RegressionNode, domain: &CompiledDomain) -> Option<Self> {
+        let mut requirements = Vec::new();
+        for (key, compares) in &node.requirements {
+            let slot = domain.slot(*key)?;
+            requirements.extend(compares.iter().map(|compare| (slot, compare.clone())));
+        }
+        Some(Self {
+            step,
+            requirements,
+            is_goal: false,
+        })
+    }
+
+    fn is_reached(&self, domain: &CompiledDomain, state: &State) -> Result<bool, PlanError> {
+        if self.is_goal {
+            return domain.is_goal(&state.values);
+        }
+        self.requirements
+            .iter()
+            .try_fold(true, |reached, (slot, compare)| {
+                Ok(domain.compare_slot(&state.values, *slot, compare)? && reached)
+            })
+    }
+}
+
+/// Searches forward from the current state until the rest of the plan can take over
+struct RepairSearch<'a> {
+    domain: &'a CompiledDomain,
+    /// Ordered by step, so the first one reached keeps the most of the plan
+    targets: &'a [Target],
+    heuristic: &'a dyn Heuristic,
+    context: HeuristicContext<'a>,
+    /// The most the kept steps of the plan can cost, which the [`Heuristic`] might have counted
+    kept_cost: Cost,
+    /// The start state, with the slots of the node being estimated written into it, to work out
+    /// the cost of [`Action`]s that depend on the state
+    state: RefCell<LocalState>,
+    /// Values of states the search is done with, to be reused for new states
+    free_values: RefCell<Vec<Box<[Option<Datum>]>>>,
+}
+
+impl RepairSearch<'_> {
+    fn target(&self, state: &State) -> Result<Option<&Target>, PlanError> {
+        for target in self.targets {
+            if target.is_reached(self.domain, state)? {
+                return Ok(Some(target));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl SearchSpace for RepairSearch<'_> {
+    type Node = State;
+
+    fn successors(
+        &self,
+        node: &State,
+        successors: &mut Vec<Successor<State>>,
+    ) -> Result<(), PlanError> {
+        let mut free = self.free_values.borrow_mut();
+        let mut state = self.state.borrow_mut();
+        for action in &self.domain.compiled_actions {
+            if let Some(next) = self.domain.successor(node, action, &mut free, &mut state)? {
+                successors.push(Successor {
+                    node: next,
+                    action: action.action,
+                    cost: self.domain.action_cost(action.action, node, &mut state),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn heuristic(&self, node: &State) -> Cost {
+        // The heuristics estimate the cost to the goal, which might be reached by the kept steps
+        // instead of by the search, so only what's left after them is sure to be searched for
+        if !self.kept_cost.is_finite() {
+            return Cost::ZERO;
+        }
+        let mut state = self.state.borrow_mut();
+        self.domain.write_slots(&node.values, &mut state);
+        (self.heuristic.estimate(&state, &self.context) - self.kept_cost).max(Cost::ZERO)
+    }
+
+    fn is_goal(&self, node: &State) -> Result<bool, PlanError> {
+        Ok(self.target(node)?.is_some())
+    }
+
+    fn recycle(&self, node: State) {
+        self.free_values.borrow_mut().push(node.values);
+    }
+}
+
+/// Like [`repair_plan`], but stops searching once one of the limits in [`PlanOptions`] is hit.
+/// Always searches from the start state, guided by [`PlanOptions::heuristic`].
+///
+/// [`PlanOptions::max_plan_length`] and [`PlanOptions::max_cost`] apply to the whole repaired
+/// plan, including the steps that are kept. When the cheapest repair doesn't fit in them, this
+/// returns [`PlanError::BudgetExceeded`] without trying others that keep less of the `plan`, so
+/// plan from scratch then.
+///
+/// To repair plans for the same [`Action`]s and [`Goal`] again and again, compile them once and
+/// use [`CompiledDomain::repair_plan`] instead.
+pub fn repair_plan_with_options(
+    state: &LocalState,
+    plan: &[Effect],
+    actions: &[Action],
+    goal: &Goal,
+    options: &PlanOptions,
+) -> Result<Plan, PlanError> {
+    CompiledDomain::new(actions, goal).repair_plan(state, plan, options)
+}
+
+pub(crate) fn repair(
+    domain: &CompiledDomain,
+    state: &LocalState,
+    plan: &[Effect],
+    options: &PlanOptions,
+    context: &mut PlanningContext,
+) -> Result<Plan, PlanError> {
+    let (actions, goal) = (domain.actions(), domain.goal());
+    let start = domain.state_from(state);
+    if domain.is_goal(&start.values)? {
+        return Err(PlanError::GoalAlreadySatisfied);
+    }
+
+    // Which of `actions` each step of the plan comes from
+    let steps: Vec<Option<usize>> = plan
+        .iter()
+        .map(|effect| {
+            actions
+                .iter()
+                .position(|action| action.key == effect.action && !action.effects.is_empty())
+        })
+        .collect();
+
+    // An empty rest of the plan only needs the goal itself
+    let mut node = RegressionNode::from_goal(goal);
+    let mut targets: Vec<Target> = Target::new(plan.len(), &node, domain)
+        .map(|target| Target {
+            is_goal: true,
+            ..target
+        })
+        .into_iter()
+        .collect();
+    // Conditions and comparisons to other keys can't be regressed, so with them in the goal the
+    // plan can't be kept
+    let keep = if goal.conditions.is_empty()
+        && goal
+            .requirements
+            .values()
+            .all(|compare| compare.other_key().is_none())
+    {
+        steps.len()
+    } else {
+        0
+    };
+    for (step, action) in steps[..keep].iter().enumerate().rev() {
+        // A step that can't be kept means none of the steps before it can be kept either
+        let Some(action) = action.map(|index| &actions[index]) else {
+            break;
+        };
+        // Closures and comparisons to other keys can only be checked against the state before
+        // the step, which isn't known yet, and conditions and some mutators can't be regressed
+        if !action.precondition_fns.is_empty()
+            || !action.conditions.is_empty()
+            || action
+                .preconditions
+                .iter()
+                .any(|(_, compare)| compare.other_key().is_some())
+            || action
+                .effects
+                .iter()
+                .flat_map(|effect| &effect.mutators)
+                .any(|mutator| !can_regress(mutator))
+        {
+            break;
+        }
+        let Some((regressed, _)) = node.regress(action)? else {
+            break;
+        };
+        let Some(target) = Target::new(step, &regressed, domain) else {
+            break;
+        };
+        targets.push(target);
+        node = regressed;
+    }
+    targets.reverse();
+
+    // The first target keeps the most steps, which cost as much as their effects say unless
+    // their cost depends on the state
+    let kept_cost = targets.first().map_or(Cost::ZERO, |target| {
+        steps[target.step..]
+            .iter()
+            .flatten()
+            .map(|index| &actions[*index].effects[0])
+            .map(|effect| {
+                if effect.cost_fn.is_some() {
+                    Cost::INFINITY
+                } else {
+                    effect.cost
+                }
+            })
+            .sum()
+    });
+    let space = RepairSearch {
+        domain,
+        targets: &targets,
+        heuristic: options.heuristic.as_deref().unwrap_or(&DistanceHeuristic),
+        context: HeuristicContext::new(domain.actions(), domain.goal()),
+        kept_cost,
+        state: RefCell::new(state.clone()),
+        free_values: RefCell::new(context.take_free_values(domain)),
+    };
+    let path = search(&space, start, options, false, &mut context.forward);
+    context.free_values = space.free_values.take();
+    let path = path?;
+    let bridge = replay_plan(state, actions, goal, path.actions.iter().copied())?;
+    let reached = domain.state_from(bridge.final_state());
+    // The search only ends at a state where the rest of the plan can take over, and targets are
+    // only made for steps from known actions
+    let kept: Option<Vec<usize>> = space
+        .target(&reached)?
+        .and_then(|target| steps[target.step..].iter().copied().collect());
+    let Some(kept) = kept else {
+        return Err(PlanError::SearchExhausted);
+    };
+
+    let plan = replay_plan(state, actions, goal, path.actions.into_iter().chain(kept))?;
+    if let Some(max) = options.max_plan_length
+        && plan.len() > max
+    {
+        return Err(PlanError::BudgetExceeded(BudgetLimit::PlanLength(max)));
+    }
+    if let Some(max) = options.max_cost
+        && plan.cost > max
+    {
+        return Err(PlanError::BudgetExceeded(BudgetLimit::Cost(max)));
+    }
+    Ok(plan)
+}
+
+/// Checks whether the rest of a `plan` still reaches the [`Goal`] from `state`, and if it
+/// doesn't, finds the cheapest way to get back to a point where the rest of it does, keeping as
+/// much of the `plan` as possible. Without any part of the `plan` left to keep, this makes a new
+/// plan from scratch.
+///
+/// `plan` holds the [`Effect`]s that are left to execute, in order, like the
+/// [`Plan::steps`] of a [`Plan`] that's partly executed. Steps of the `plan` are matched to
+/// `actions` by name.
+pub fn repair_plan(
+    state: &LocalState,
+    plan: &[Effect],
+    actions: &[Action],
+    goal: &Goal,
+) -> Result<Plan, PlanError> {
+    repair_plan_with_options(state, plan, actions, goal, &PlanOptions::default())
+}
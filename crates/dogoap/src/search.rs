@@ -0,0 +1,306 @@
+//! The best-first search that both [`PlanningStrategy`](crate::planner::PlanningStrategy)s use
+//! to find their way through the states, within the limits set by [`PlanOptions`]
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::hash::{BuildHasherDefault, Hash};
+use std::time::Instant;
+
+use indexmap::IndexMap;
+use rustc_hash::FxHasher;
+
+use crate::cost::Cost;
+use crate::error::{BudgetLimit, PlanError};
+use crate::planner::PlanOptions;
+
+pub(crate) type FxIndexMap<K, V> = IndexMap<K, V, BuildHasherDefault<FxHasher>>;
+
+/// What the search needs to know about the states it searches through
+pub(crate) trait SearchSpace {
+    type Node: Clone + Eq + Hash;
+
+    /// Pushes every node reachable from `node` in one step onto `successors`
+    fn successors(
+        &self,
+        node: &Self::Node,
+        successors: &mut Vec<Successor<Self::Node>>,
+    ) -> Result<(), PlanError>;
+
+    /// Estimates the remaining cost from `node`. Should never overestimate it, or the path
+    /// found might not be the cheapest one.
+    fn heuristic(&self, node: &Self::Node) -> Cost;
+
+    fn is_goal(&self, node: &Self::Node) -> Result<bool, PlanError>;
+
+    /// Takes back a node the search is done with, so its memory can be reused
+    fn recycle(&self, _node: Self::Node) {}
+}
+
+pub(crate) struct Successor<N> {
+    pub node: N,
+    /// Index of the [`Action`](crate::action::Action) that leads to `node`
+    pub action: usize,
+    pub cost: Cost,
+}
+
+/// The path that was found, from the start node to the goal node
+pub(crate) struct Path {
+    /// The index of the action taken at each step
+    pub actions: Vec<usize>,
+    /// Set when the path doesn't reach the goal, as the search ran out of budget
+    pub partial: Option<BudgetLimit>,
+}
+
+/// What the search remembers about every node it has seen, next to the node itself. There can
+/// be a lot of them, so indices are stored as `u32`.
+struct Visited {
+    /// The index of the node we came from, and the action that took us here
+    step: Option<(u32, u32)>,
+    /// How many actions it takes to get here
+    length: u32,
+    cost: Cost,
+}
+
+fn compact(index: usize) -> u32 {
+    u32::try_from(index).expect("Searched more nodes or actions than fit in a u32")
+}
+
+#[derive(PartialEq, Eq)]
+struct Candidate {
+    estimate: Cost,
+    /// Random with [`PlanOptions::seed`], otherwise always 0
+    tie: u64,
+    cost: Cost,
+    index: usize,
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // `BinaryHeap` is a max-heap, so the lowest estimate needs to compare as the greatest.
+        // On equal estimates, the node that is furthest along goes first, then the oldest one,
+        // unless a seed shuffles them.
+        other
+            .estimate
+            .cmp(&self.estimate)
+            .then(other.tie.cmp(&self.tie))
+            .then(self.cost.cmp(&other.cost))
+            .then(other.index.cmp(&self.index))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Orders candidates with the same estimate randomly, but the same way for the same seed
+struct TieBreaker(Option<u64>);
+
+impl TieBreaker {
+    fn next(&mut self) -> u64 {
+        let Some(state) = &mut self.0 else {
+            return 0;
+        };
+        // SplitMix64
+        *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = *state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// The memory [`search`] works in, which can be kept around to reuse it for the next search
+pub(crate) struct SearchBuffers<N> {
+    /// Keyed by the node and, with [`PlanOptions::max_plan_length`], by the number of actions it
+    /// takes to get there. Otherwise a cheaper but longer way to a node would replace a shorter
+    /// one, and the goal might be out of reach from it within the limit, while it wasn't from
+    /// the shorter one.
+    visited: FxIndexMap<(N, u32), Visited>,
+    open: BinaryHeap<Candidate>,
+    successors: Vec<Successor<N>>,
+}
+
+impl<N> Default for SearchBuffers<N> {
+    fn default() -> Self {
+        Self {
+            visited: FxIndexMap::default(),
+            open: BinaryHeap::new(),
+            successors: Vec::new(),
+        }
+    }
+}
+
+/// A* search from `start` until [`SearchSpace::is_goal`] is reached, or until one of the limits
+/// in `options` is hit.
+///
+/// With `anytime`, hitting a limit returns the path to the node with the lowest heuristic seen so
+/// far instead, as long as that gets us anywhere.
+pub(crate) fn search<S: SearchSpace>(
+    space: &S,
+    start: S::Node,
+    options: &PlanOptions,
+    anytime: bool,
+    buffers: &mut SearchBuffers<S::Node>,
+) -> Result<Path, PlanError> {
+    let path = search_in(space, start, options, anytime, buffers);
+    for ((node, _), _) in buffers.visited.drain(..) {
+        space.recycle(node);
+    }
+    buffers.open.clear();
+    for successor in buffers.successors.drain(..) {
+        space.recycle(successor.node);
+    }
+    path
+}
+
+fn search_in<S: SearchSpace>(
+    space: &S,
+    start: S::Node,
+    options: &PlanOptions,
+    anytime: bool,
+    SearchBuffers {
+        visited,
+        open,
+        successors,
+    }: &mut SearchBuffers<S::Node>,
+) -> Result<Path, PlanError> {
+    // Only ask for the time when there is a timeout, not every platform has a clock
+    let started = options.timeout.map(|timeout| (Instant::now(), timeout));
+    // The heuristic, cost and index of the node closest to the goal
+    let mut best = (space.heuristic(&start), Cost::ZERO, 0);
+    visited.insert(
+        (start, 0),
+        Visited {
+            step: None,
+            cost: Cost::ZERO,
+            length: 0,
+        },
+    );
+    let mut tie_breaker = TieBreaker(options.seed);
+    open.push(Candidate {
+        estimate: Cost::ZERO,
+        tie: 0,
+        cost: Cost::ZERO,
+        index: 0,
+    });
+    let mut expanded = 0;
+    // Set when a node was skipped because of a limit, as the goal might have been behind it
+    let mut pruned = None;
+
+    let limit = 'search: {
+        while let Some(Candidate { cost, index, .. }) = open.pop() {
+            let ((node, _), visit) = visited
+                .get_index(index)
+                .expect("Every candidate should point to a visited node");
+            if cost > visit.cost {
+                // We've found a cheaper way to this node since this candidate was pushed
+                continue;
+            }
+            if space.is_goal(node)? {
+                return Ok(reconstruct_path(visited, index));
+            }
+            if let Some(max) = options.max_expanded_nodes
+                && expanded >= max
+            {
+                break 'search BudgetLimit::ExpandedNodes(max);
+            }
+            if let Some((started, timeout)) = started
+                && started.elapsed() >= timeout
+            {
+                break 'search BudgetLimit::Time(timeout);
+            }
+            expanded += 1;
+
+            let length = visit.length + 1;
+            if let Some(max) = options.max_plan_length
+                && length as usize > max
+            {
+                pruned.get_or_insert(BudgetLimit::PlanLength(max));
+                continue;
+            }
+
+            space.successors(node, successors)?;
+            for successor in successors.drain(..) {
+                let new_cost = cost + successor.cost;
+                if let Some(max) = options.max_cost
+                    && new_cost > max
+                {
+                    pruned.get_or_insert(BudgetLimit::Cost(max));
+                    continue;
+                }
+                let visit = Visited {
+                    step: Some((compact(index), compact(successor.action))),
+                    cost: new_cost,
+                    length,
+                };
+                let layer = if options.max_plan_length.is_some() {
+                    length
+                } else {
+                    0
+                };
+                let key = (successor.node, layer);
+                let new_index = match visited.get_index_of(&key) {
+                    Some(existing) => {
+                        space.recycle(key.0);
+                        if visited[existing].cost <= new_cost {
+                            continue;
+                        }
+                        visited[existing] = visit;
+                        existing
+                    }
+                    None => visited.insert_full(key, visit).0,
+                };
+                let ((new_node, _), _) = visited
+                    .get_index(new_index)
+                    .expect("The node was just inserted");
+                let heuristic = space.heuristic(new_node);
+                if (heuristic, new_cost) < (best.0, best.1) {
+                    best = (heuristic, new_cost, new_index);
+                }
+                open.push(Candidate {
+                    estimate: new_cost + heuristic,
+                    tie: tie_breaker.next(),
+                    cost: new_cost,
+                    index: new_index,
+                });
+            }
+        }
+        match pruned {
+            Some(limit) => limit,
+            None => return Err(PlanError::SearchExhausted),
+        }
+    };
+
+    let (_, _, best_index) = best;
+    if anytime && best_index != 0 {
+        let mut path = reconstruct_path(visited, best_index);
+        path.partial = Some(limit);
+        Ok(path)
+    } else {
+        Err(PlanError::BudgetExceeded(limit))
+    }
+}
+
+fn reconstruct_path<N>(visited: &FxIndexMap<(N, u32), Visited>, goal: usize) -> Path {
+    let mut actions = vec![];
+    let mut index = goal;
+    loop {
+        let (_, visit) = visited
+            .get_index(index)
+            .expect("Every step should point to a visited node");
+        match visit.step {
+            Some((parent, action)) => {
+                actions.push(action as usize);
+                index = parent as usize;
+            }
+            None => break,
+        }
+    }
+    actions.reverse();
+    Path {
+        actions,
+        partial: None,
+    }
+}
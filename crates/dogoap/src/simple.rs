@@ -5,7 +5,7 @@ use crate::prelude::*;
 /// Creates an [`Action`] that sets a key to a value.
 pub fn simple_action(
     name: impl Into<String>,
-    key_to_mutate: impl Into<String>,
+    key_to_mutate: impl Into<Key>,
     from_value: impl Into<Datum>,
 ) -> Action {
     Action::new(name).with_mutator(Mutator::set(key_to_mutate, from_value))
@@ -14,7 +14,7 @@ pub fn simple_action(
 /// Creates an [`Action`] that sets multiple keys to values.
 pub fn simple_multi_mutate_action(
     name: impl Into<String>,
-    muts: impl IntoIterator<Item = (impl Into<String>, impl Into<Datum>)>,
+    muts: impl IntoIterator<Item = (impl Into<Key>, impl Into<Datum>)>,
 ) -> Action {
     let mut mutators = vec![];
 
@@ -26,11 +26,14 @@ pub fn simple_multi_mutate_action(
     Action {
         key: name.clone(),
         preconditions: vec![],
+        conditions: vec![],
+        precondition_fns: vec![],
         effects: vec![Effect {
             action: name,
             mutators,
             state: LocalState::new(),
-            cost: 1,
+            cost: Cost::ONE,
+            cost_fn: None,
         }],
     }
 }
@@ -38,7 +41,7 @@ pub fn simple_multi_mutate_action(
 /// Creates an [`Action`] that increments a key by a value.
 pub fn simple_increment_action(
     name: &str,
-    key_to_mutate: impl Into<String>,
+    key_to_mutate: impl Into<Key>,
     from_value: impl Into<Datum>,
 ) -> Action {
     Action::new(name).with_mutator(Mutator::increment(key_to_mutate, from_value))
@@ -47,7 +50,7 @@ pub fn simple_increment_action(
 /// Creates an [`Action`] that decrements a key by a value.
 pub fn simple_decrement_action(
     name: &str,
-    key_to_mutate: impl Into<String>,
+    key_to_mutate: impl Into<Key>,
     from_value: impl Into<Datum>,
 ) -> Action {
     Action::new(name).with_mutator(Mutator::decrement(key_to_mutate, from_value))
@@ -1,12 +1,55 @@
 //! Integration tests
 
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::time::Duration;
+
 use dogoap::{
+    heuristic::{
+        DistanceHeuristic, FfHeuristic, HAddHeuristic, HMaxHeuristic, HeuristicContext,
+        WeightedAStar, WeightedDistanceHeuristic, ZeroHeuristic,
+    },
+    planner::{
+        CompiledDomain, IncrementalPlanner, PlanOptions, PlanningContext, PlanningStrategy,
+        SimulationError, StepFailure, estimate_remaining_cost, make_plan_anytime,
+        make_plan_with_options, make_plan_with_strategy, make_plans, repair_plan,
+        repair_plan_with_options, simulate_plan,
+    },
     prelude::*,
     simple::{
         simple_action, simple_decrement_action, simple_increment_action, simple_multi_mutate_action,
     },
 };
 
+/// The planner's estimate must never be more than the cost that is actually left, or the plan it
+/// finds might not be the cheapest one. Checks that the plan costs as much as the one Dijkstra's
+/// algorithm finds, and that no state along it is overestimated, for the default heuristic and
+/// for `h_max`.
+fn assert_admissible(start: &LocalState, actions: &[Action], goal: &Goal) {
+    let plan = make_plan(start, actions, goal).unwrap();
+    let cost = plan.cost;
+    let dijkstra = PlanOptions::new().with_heuristic(ZeroHeuristic);
+    let cheapest = make_plan_with_options(start, actions, goal, &dijkstra)
+        .unwrap()
+        .cost;
+    assert!(
+        (cost.value() - cheapest.value()).abs() < 1e-9,
+        "Found a plan that costs {cost}, but the cheapest one costs {cheapest}"
+    );
+    let context = HeuristicContext::new(actions, goal);
+    let mut spent = Cost::ZERO;
+    let step_costs = std::iter::once(Cost::ZERO).chain(plan.iter().map(|step| step.cost));
+    for (state, step_cost) in plan.states().zip(step_costs) {
+        spent += step_cost;
+        let estimate = estimate_remaining_cost(state, actions, goal);
+        let h_max = HMaxHeuristic.estimate(state, &context);
+        assert!(
+            estimate.max(h_max) <= cost - spent,
+            "Estimated {estimate} (h_max {h_max}) for {state:?}, but only {} is left",
+            cost - spent
+        );
+    }
+}
+
 // One action that sets one field
 #[test]
 fn test_basic_bool_setting() {
@@ -20,19 +63,22 @@ fn test_basic_bool_setting() {
         action: "eat".to_string(),
         mutators: vec![eat_mutator.clone()],
         state: LocalState::new(),
-        cost: 1,
+        cost: Cost::ONE,
+        cost_fn: None,
     };
 
     let eat_action = Action {
         key: "eat".to_string(),
         preconditions: vec![],
+        conditions: vec![],
+        precondition_fns: vec![],
         effects: vec![eat_consequence],
     };
 
     let actions: Vec<Action> = vec![eat_action];
 
-    let plan = get_effects_from_plan(make_plan(&start, &actions[..], &goal).unwrap().0)
-        .collect::<Vec<_>>();
+    let plan =
+        get_effects_from_plan(make_plan(&start, &actions[..], &goal).unwrap()).collect::<Vec<_>>();
     assert_eq!(1, plan.len());
 
     let cons = plan.first().unwrap();
@@ -41,6 +87,8 @@ fn test_basic_bool_setting() {
 
     let expected_state = LocalState::new().with_datum("is_hungry", false);
     assert_eq!(expected_state, cons.state);
+
+    assert_admissible(&start, &actions[..], &goal);
 }
 
 // The state is already what we need!
@@ -56,23 +104,22 @@ fn test_no_actions_needed() {
         action: "eat".to_string(),
         mutators: vec![eat_mutator.clone()],
         state: LocalState::new(),
-        cost: 1,
+        cost: Cost::ONE,
+        cost_fn: None,
     };
 
     let eat_action = Action {
         key: "eat".to_string(),
         preconditions: vec![],
+        conditions: vec![],
+        precondition_fns: vec![],
         effects: vec![eat_consequence],
     };
 
     let actions: Vec<Action> = vec![eat_action];
 
-    let (plan, plan_cost) = make_plan(&start, &actions[..], &goal).unwrap();
-    assert_eq!(1, plan.len());
-    assert_eq!(0, plan_cost);
-
-    let expected_state = LocalState::new().with_datum("is_hungry", false);
-    assert_eq!(expected_state, plan.first().unwrap().state().clone());
+    let plan = make_plan(&start, &actions[..], &goal);
+    assert_eq!(Err(PlanError::GoalAlreadySatisfied), plan);
 }
 
 // Shorthand for one action that sets one field
@@ -88,14 +135,16 @@ fn test_simple_action() {
 
     let actions: Vec<Action> = vec![eat_action];
 
-    let plan = get_effects_from_plan(make_plan(&start, &actions[..], &goal).unwrap().0)
-        .collect::<Vec<_>>();
+    let plan =
+        get_effects_from_plan(make_plan(&start, &actions[..], &goal).unwrap()).collect::<Vec<_>>();
     assert_eq!(1, plan.len());
 
     let cons = plan.first().unwrap().clone();
     assert_eq!(1, cons.mutators.len());
     assert_eq!(eat_mutator, cons.mutators.first().unwrap().clone());
     assert_eq!(expected_state, cons.state);
+
+    assert_admissible(&start, &actions[..], &goal);
 }
 
 // State with two fields + two actions each mutating their fields
@@ -120,7 +169,7 @@ fn test_two_bools() {
 
     let plan = make_plan(&start, &actions[..], &goal).unwrap();
 
-    let cons = get_effects_from_plan(plan.0).collect::<Vec<_>>();
+    let cons = get_effects_from_plan(plan).collect::<Vec<_>>();
     assert_eq!(2, cons.len());
 
     let first_cons = cons.first().unwrap();
@@ -130,6 +179,8 @@ fn test_two_bools() {
     assert_eq!(1, second_cons.mutators.len());
 
     assert_eq!(expected_state, second_cons.state);
+
+    assert_admissible(&start, &actions[..], &goal);
 }
 
 // State with two fields + two actions each mutating their fields
@@ -168,7 +219,7 @@ fn test_four_bools() {
 
     let plan = make_plan(&start, &actions[..], &goal).unwrap();
 
-    let cons = get_effects_from_plan(plan.0).collect::<Vec<_>>();
+    let cons = get_effects_from_plan(plan).collect::<Vec<_>>();
     assert_eq!(4, cons.len());
 
     let first_cons = cons.first().unwrap();
@@ -189,6 +240,8 @@ fn test_four_bools() {
         .with_datum("is_fit", true)
         .with_datum("is_dirty", false);
     assert_eq!(expected_state, cons.last().unwrap().state);
+
+    assert_admissible(&start, &actions[..], &goal);
 }
 
 enum TestLocation {
@@ -223,7 +276,7 @@ fn test_enums() {
     let actions: Vec<Action> = vec![go_outside_action, go_to_market_action, go_to_ramen_action];
 
     let plan = make_plan(&start, &actions[..], &goal);
-    let effects = get_effects_from_plan(plan.unwrap().0).collect::<Vec<_>>();
+    let effects = get_effects_from_plan(plan.unwrap()).collect::<Vec<_>>();
 
     assert_eq!(3, effects.len());
 
@@ -238,6 +291,8 @@ fn test_enums() {
 
     // Take only the last one
     assert_eq!(expected_state, cons.state);
+
+    assert_admissible(&start, &actions[..], &goal);
 }
 
 // // eat action can only be done with not tired
@@ -263,8 +318,8 @@ fn test_preconditions() {
 
     let actions: Vec<Action> = vec![eat_action, sleep_action];
 
-    let plan = get_effects_from_plan(make_plan(&start, &actions[..], &goal).unwrap().0)
-        .collect::<Vec<_>>();
+    let plan =
+        get_effects_from_plan(make_plan(&start, &actions[..], &goal).unwrap()).collect::<Vec<_>>();
     assert_eq!(3, plan.len());
 
     let first_cons = plan.first().unwrap();
@@ -280,6 +335,8 @@ fn test_preconditions() {
         expected_state, third_cons.state,
         "Final state wasn't what we expected"
     );
+
+    assert_admissible(&start, &actions[..], &goal);
 }
 
 // We can use ints too!
@@ -295,8 +352,8 @@ fn test_int_increment() {
 
     let actions: Vec<Action> = vec![eat_action];
 
-    let plan = get_effects_from_plan(make_plan(&start, &actions[..], &goal).unwrap().0)
-        .collect::<Vec<_>>();
+    let plan =
+        get_effects_from_plan(make_plan(&start, &actions[..], &goal).unwrap()).collect::<Vec<_>>();
     assert_eq!(5, plan.len());
 
     for cons in &plan {
@@ -305,6 +362,8 @@ fn test_int_increment() {
     }
 
     assert_eq!(expected_state, plan.last().unwrap().state);
+
+    assert_admissible(&start, &actions[..], &goal);
 }
 
 #[test]
@@ -319,8 +378,8 @@ fn test_int_decrement() {
 
     let actions: Vec<Action> = vec![eat_action];
 
-    let plan = get_effects_from_plan(make_plan(&start, &actions[..], &goal).unwrap().0)
-        .collect::<Vec<_>>();
+    let plan =
+        get_effects_from_plan(make_plan(&start, &actions[..], &goal).unwrap()).collect::<Vec<_>>();
     assert_eq!(7, plan.len());
 
     for cons in &plan {
@@ -329,6 +388,8 @@ fn test_int_decrement() {
     }
 
     assert_eq!(expected_state, plan.last().unwrap().state);
+
+    assert_admissible(&start, &actions[..], &goal);
 }
 
 #[test]
@@ -343,8 +404,8 @@ fn test_float_increment() {
 
     let actions: Vec<Action> = vec![eat_action];
 
-    let plan = get_effects_from_plan(make_plan(&start, &actions[..], &goal).unwrap().0)
-        .collect::<Vec<_>>();
+    let plan =
+        get_effects_from_plan(make_plan(&start, &actions[..], &goal).unwrap()).collect::<Vec<_>>();
     assert_eq!(5, plan.len());
 
     for cons in &plan {
@@ -353,6 +414,8 @@ fn test_float_increment() {
     }
 
     assert_eq!(expected_state, plan.last().unwrap().state);
+
+    assert_admissible(&start, &actions[..], &goal);
 }
 
 // GreaterThanEquals can be useful sometimes too!
@@ -368,7 +431,7 @@ fn test_greater_than_equals() {
     let actions: Vec<Action> = vec![eat_action];
 
     let plan = make_plan(&start, &actions[..], &goal).unwrap();
-    let effects = get_effects_from_plan(plan.0.clone()).collect::<Vec<_>>();
+    let effects = get_effects_from_plan(plan.clone()).collect::<Vec<_>>();
 
     assert_eq!(9, effects.len());
 
@@ -381,6 +444,8 @@ fn test_greater_than_equals() {
     }
 
     assert_eq!(expected_state, effects.last().unwrap().state);
+
+    assert_admissible(&start, &actions[..], &goal);
 }
 
 #[test]
@@ -410,19 +475,22 @@ fn test_long_plan() {
                 Mutator::increment("hunger", 20_i64),
             ],
             state: LocalState::default(),
-            cost: 1,
+            cost: Cost::ONE,
+            cost_fn: None,
         })
         .with_precondition(("hunger", Compare::less_than_equals(50_i64)))
         .with_precondition(("energy", Compare::greater_than_equals(50_i64)));
     let actions: Vec<Action> = vec![sleep_action, eat_action, rob_people];
 
-    let plan = get_effects_from_plan(make_plan(&start, &actions[..], &goal).unwrap().0)
-        .collect::<Vec<_>>();
+    let plan =
+        get_effects_from_plan(make_plan(&start, &actions[..], &goal).unwrap()).collect::<Vec<_>>();
     println!("b");
 
     assert_eq!(50, plan.len());
 
     assert_eq!(expected_state, plan.last().unwrap().state);
+
+    assert_admissible(&start, &actions[..], &goal);
 }
 
 #[test]
@@ -450,11 +518,1560 @@ fn test_prefer_lower_cost_plan() {
     let actions = [cheap_action, expensive_action];
 
     let plan = make_plan(&start, &actions[..], &goal).unwrap();
-    let effects = get_effects_from_plan(plan.0.clone()).collect::<Vec<_>>();
+    let effects = get_effects_from_plan(plan.clone()).collect::<Vec<_>>();
 
     println!("Found plan:");
     println!("{plan:#?}");
 
     assert_eq!(10, effects.len());
     assert_eq!(expected_state, effects.last().unwrap().state);
+
+    assert_admissible(&start, &actions[..], &goal);
+}
+
+// Planning backwards from the goal should find a plan just as cheap as planning forwards
+#[test]
+fn test_goal_to_start_matches_start_to_goal() {
+    let start = LocalState::new()
+        .with_datum("is_hungry", true)
+        .with_datum("is_tired", true)
+        .with_datum("is_fit", false)
+        .with_datum("is_dirty", false);
+
+    let goal = Goal::new()
+        .with_req("is_hungry", Compare::equals(false))
+        .with_req("is_tired", Compare::equals(false))
+        .with_req("is_fit", Compare::equals(true))
+        .with_req("is_dirty", Compare::equals(false));
+
+    let eat_action = simple_action("eat", "is_hungry", false);
+    let sleep_action =
+        simple_multi_mutate_action("sleep", vec![("is_tired", false), ("is_hungry", true)]);
+    let train_action = simple_multi_mutate_action(
+        "train",
+        vec![("is_tired", true), ("is_dirty", true), ("is_fit", true)],
+    );
+    let shower_action =
+        simple_multi_mutate_action("shower", vec![("is_tired", true), ("is_dirty", false)]);
+
+    let actions: Vec<Action> = vec![eat_action, sleep_action, train_action, shower_action];
+
+    let forwards =
+        make_plan_with_strategy(PlanningStrategy::StartToGoal, &start, &actions[..], &goal)
+            .unwrap();
+    let backwards =
+        make_plan_with_strategy(PlanningStrategy::GoalToStart, &start, &actions[..], &goal)
+            .unwrap();
+
+    assert_eq!(forwards.cost, backwards.cost);
+    assert_eq!(forwards.final_state(), backwards.final_state());
+
+    assert_admissible(&start, &actions[..], &goal);
+}
+
+#[test]
+fn test_goal_to_start_with_preconditions_and_increments() {
+    let start = LocalState::new()
+        .with_datum("energy", 30_i64)
+        .with_datum("hunger", 70_i64)
+        .with_datum("gold", 0_i64);
+
+    let goal = Goal::new().with_req("gold", Compare::equals(7_i64));
+
+    let sleep_action = simple_increment_action("sleep", "energy", 10_i64);
+
+    let eat_action = simple_decrement_action("eat", "hunger", 10_i64)
+        .with_precondition(("energy", Compare::greater_than_equals(25_i64)));
+
+    let rob_people = Action::new("rob")
+        .with_mutator(Mutator::increment("gold", 1_i64))
+        .with_mutator(Mutator::decrement("energy", 5_i64))
+        .with_mutator(Mutator::increment("hunger", 5_i64))
+        .with_precondition(("hunger", Compare::less_than_equals(50_i64)))
+        .with_precondition(("energy", Compare::greater_than_equals(50_i64)));
+
+    let actions: Vec<Action> = vec![sleep_action, eat_action, rob_people];
+
+    let forwards =
+        make_plan_with_strategy(PlanningStrategy::StartToGoal, &start, &actions[..], &goal)
+            .unwrap();
+    let backwards =
+        make_plan_with_strategy(PlanningStrategy::GoalToStart, &start, &actions[..], &goal)
+            .unwrap();
+
+    assert_eq!(forwards.cost, backwards.cost);
+
+    let effects = get_effects_from_plan(backwards).collect::<Vec<_>>();
+    assert_eq!(
+        7,
+        effects
+            .iter()
+            .filter(|effect| effect.action == "rob")
+            .count()
+    );
+    assert_eq!(
+        Some(&Datum::I64(7)),
+        effects.last().unwrap().state.get("gold")
+    );
+
+    assert_admissible(&start, &actions[..], &goal);
+}
+
+// Actions that have nothing to do with the goal are never used by the regressive search
+#[test]
+fn test_goal_to_start_ignores_irrelevant_actions() {
+    let start = LocalState::new()
+        .with_datum("is_hungry", true)
+        .with_datum("has_food", false)
+        .with_datum("is_tired", true);
+
+    let goal = Goal::new().with_req("is_hungry", Compare::equals(false));
+
+    let eat_action = simple_action("eat", "is_hungry", false)
+        .with_precondition(("has_food", Compare::equals(true)));
+    let buy_food_action = simple_action("buy_food", "has_food", true);
+    let sleep_action = simple_action("sleep", "is_tired", false);
+
+    let actions: Vec<Action> = vec![sleep_action, buy_food_action, eat_action];
+
+    let plan = make_plan_with_strategy(PlanningStrategy::GoalToStart, &start, &actions[..], &goal)
+        .unwrap();
+    let cost = plan.cost;
+    let effects = get_effects_from_plan(plan).collect::<Vec<_>>();
+
+    assert_eq!(Cost::from(2), cost);
+    assert_eq!(
+        vec!["buy_food", "eat"],
+        effects
+            .iter()
+            .map(|effect| effect.action.as_str())
+            .collect::<Vec<_>>()
+    );
+
+    assert_admissible(&start, &actions[..], &goal);
+}
+
+#[test]
+fn test_goal_to_start_unreachable() {
+    let start = LocalState::new().with_datum("is_hungry", true);
+
+    let goal = Goal::new().with_req("is_hungry", Compare::equals(false));
+
+    let sleep_action = simple_action("sleep", "is_hungry", true);
+
+    let actions: Vec<Action> = vec![sleep_action];
+
+    let plan = make_plan_with_strategy(PlanningStrategy::GoalToStart, &start, &actions[..], &goal);
+    assert_eq!(Err(PlanError::SearchExhausted), plan);
+}
+
+#[test]
+fn test_unreachable_goal() {
+    let start = LocalState::new().with_datum("is_hungry", true);
+
+    let goal = Goal::new().with_req("is_hungry", Compare::equals(false));
+
+    let sleep_action = simple_action("sleep", "is_hungry", true);
+
+    let actions: Vec<Action> = vec![sleep_action];
+
+    let plan = make_plan(&start, &actions[..], &goal);
+    assert_eq!(Err(PlanError::SearchExhausted), plan);
+}
+
+// Mistakes in how actions are defined are returned as errors rather than panicking
+#[test]
+fn test_missing_key_in_precondition() {
+    let start = LocalState::new().with_datum("is_hungry", true);
+
+    let goal = Goal::new().with_req("is_hungry", Compare::equals(false));
+
+    let eat_action = simple_action("eat", "is_hungry", false)
+        .with_precondition(("has_food", Compare::equals(true)));
+
+    let actions: Vec<Action> = vec![eat_action];
+
+    for strategy in [PlanningStrategy::StartToGoal, PlanningStrategy::GoalToStart] {
+        let plan = make_plan_with_strategy(strategy, &start, &actions[..], &goal);
+        assert_eq!(
+            Err(PlanError::MissingKey("has_food".into())),
+            plan,
+            "{strategy:?}"
+        );
+    }
+}
+
+// Keys are sorted by name, not by which one happened to be used first
+#[test]
+fn test_keys_sorted_by_name() {
+    let start = LocalState::new()
+        .with_datum("sorted_zebra", false)
+        .with_datum("sorted_apple", false);
+    let goal = Goal::from_reqs(&[
+        ("sorted_zebra", Compare::equals(true)),
+        ("sorted_apple", Compare::equals(true)),
+    ]);
+    let keys: Vec<&str> = goal.requirements.keys().map(|key| key.as_str()).collect();
+    assert_eq!(vec!["sorted_apple", "sorted_zebra"], keys);
+
+    let actions = vec![simple_multi_mutate_action(
+        "both",
+        [("sorted_zebra", true), ("sorted_apple", true)],
+    )];
+    let formatted = format_plan(make_plan(&start, &actions, &goal).unwrap());
+    let apple = formatted.find("sorted_apple").unwrap();
+    let zebra = formatted.find("sorted_zebra").unwrap();
+    assert!(apple < zebra);
+}
+
+#[test]
+fn test_missing_key_in_goal() {
+    let start = LocalState::new().with_datum("is_hungry", true);
+
+    let goal = Goal::new().with_req("is_tired", Compare::equals(false));
+
+    let actions: Vec<Action> = vec![simple_action("sleep", "is_tired", false)];
+
+    let plan = make_plan(&start, &actions[..], &goal);
+    assert_eq!(Err(PlanError::MissingKey("is_tired".into())), plan);
+}
+
+#[test]
+fn test_type_mismatch_in_mutator() {
+    let start = LocalState::new().with_datum("energy", 0_i64);
+
+    let goal = Goal::new().with_req("energy", Compare::greater_than_equals(10_i64));
+
+    let eat_action = simple_increment_action("eat", "energy", 5.0_f64);
+
+    let actions: Vec<Action> = vec![eat_action];
+
+    for strategy in [PlanningStrategy::StartToGoal, PlanningStrategy::GoalToStart] {
+        let plan = make_plan_with_strategy(strategy, &start, &actions[..], &goal);
+        assert!(
+            matches!(
+                plan,
+                Err(PlanError::TypeMismatch {
+                    key: Some(ref key),
+                    ..
+                }) if key == "energy"
+            ),
+            "{strategy:?}: {plan:?}"
+        );
+    }
+}
+
+// Incrementing by 2 never reaches an odd number, so without a limit this would search forever
+fn endless_search() -> (LocalState, Vec<Action>, Goal) {
+    let start = LocalState::new().with_datum("energy", 0_i64);
+    let goal = Goal::new().with_req("energy", Compare::equals(7_i64));
+    let actions = vec![simple_increment_action("rest", "energy", 2_i64)];
+    (start, actions, goal)
+}
+
+#[test]
+fn test_max_expanded_nodes() {
+    let (start, actions, goal) = endless_search();
+
+    for strategy in [PlanningStrategy::StartToGoal, PlanningStrategy::GoalToStart] {
+        let options = PlanOptions::new()
+            .with_strategy(strategy)
+            .with_max_expanded_nodes(100);
+        let plan = make_plan_with_options(&start, &actions[..], &goal, &options);
+        assert_eq!(
+            Err(PlanError::BudgetExceeded(BudgetLimit::ExpandedNodes(100))),
+            plan,
+            "{strategy:?}"
+        );
+    }
+}
+
+#[test]
+fn test_max_plan_length() {
+    let (start, actions, goal) = endless_search();
+
+    for strategy in [PlanningStrategy::StartToGoal, PlanningStrategy::GoalToStart] {
+        let options = PlanOptions::new()
+            .with_strategy(strategy)
+            .with_max_plan_length(10);
+        let plan = make_plan_with_options(&start, &actions[..], &goal, &options);
+        assert_eq!(
+            Err(PlanError::BudgetExceeded(BudgetLimit::PlanLength(10))),
+            plan,
+            "{strategy:?}"
+        );
+    }
+}
+
+#[test]
+fn test_max_cost() {
+    let (start, actions, goal) = endless_search();
+
+    for strategy in [PlanningStrategy::StartToGoal, PlanningStrategy::GoalToStart] {
+        let options = PlanOptions::new().with_strategy(strategy).with_max_cost(20);
+        let plan = make_plan_with_options(&start, &actions[..], &goal, &options);
+        assert_eq!(
+            Err(PlanError::BudgetExceeded(BudgetLimit::Cost(Cost::from(20)))),
+            plan,
+            "{strategy:?}"
+        );
+    }
+}
+
+#[test]
+fn test_timeout() {
+    let (start, actions, goal) = endless_search();
+
+    let options = PlanOptions::new().with_timeout(Duration::from_millis(10));
+    let plan = make_plan_with_options(&start, &actions[..], &goal, &options);
+    assert_eq!(
+        Err(PlanError::BudgetExceeded(BudgetLimit::Time(
+            Duration::from_millis(10)
+        ))),
+        plan
+    );
+}
+
+// Limits that leave enough room for the plan don't change it
+#[test]
+fn test_limits_fit_plan() {
+    let start = LocalState::new().with_datum("energy", 0_i64);
+    let goal = Goal::new().with_req("energy", Compare::equals(6_i64));
+    let actions = [simple_increment_action("rest", "energy", 2_i64)];
+
+    let options = PlanOptions::new()
+        .with_max_plan_length(3)
+        .with_max_cost(3)
+        .with_max_expanded_nodes(3);
+    let plan = make_plan_with_options(&start, &actions[..], &goal, &options).unwrap();
+    let cost = plan.cost;
+    assert_eq!(3, get_effects_from_plan(plan).count());
+    assert_eq!(Cost::from(3), cost);
+
+    let options = options.with_max_plan_length(2);
+    let plan = make_plan_with_options(&start, &actions[..], &goal, &options);
+    assert_eq!(
+        Err(PlanError::BudgetExceeded(BudgetLimit::PlanLength(2))),
+        plan
+    );
+
+    assert_admissible(&start, &actions[..], &goal);
+}
+
+// A cheaper way to a state that takes too many steps doesn't hide a dearer one that fits
+#[test]
+fn test_max_plan_length_keeps_shorter_path() {
+    let start = LocalState::new()
+        .with_datum("position", 0_i64)
+        .with_datum("is_home", false);
+    let goal = Goal::new().with_req("is_home", Compare::equals(true));
+    let actions = [
+        simple_action("walk", "position", 1_i64)
+            .with_precondition(("position", Compare::equals(0_i64))),
+        simple_action("walk_on", "position", 2_i64)
+            .with_precondition(("position", Compare::equals(1_i64))),
+        simple_action("jump", "position", 2_i64)
+            .set_cost(3)
+            .with_precondition(("position", Compare::equals(0_i64))),
+        simple_action("enter", "is_home", true)
+            .with_precondition(("position", Compare::equals(2_i64))),
+    ];
+
+    for strategy in [PlanningStrategy::StartToGoal, PlanningStrategy::GoalToStart] {
+        let options = PlanOptions::new().with_strategy(strategy);
+        let plan = make_plan_with_options(&start, &actions[..], &goal, &options).unwrap();
+        assert_eq!(
+            vec!["walk", "walk_on", "enter"],
+            plan.actions().collect::<Vec<_>>(),
+            "{strategy:?}"
+        );
+
+        let options = options.with_max_plan_length(2);
+        let plan = make_plan_with_options(&start, &actions[..], &goal, &options).unwrap();
+        assert_eq!(
+            vec!["jump", "enter"],
+            plan.actions().collect::<Vec<_>>(),
+            "{strategy:?}"
+        );
+        assert_eq!(Cost::from(4), plan.cost, "{strategy:?}");
+    }
+}
+
+// When the budget runs out, anytime planning returns the plan that gets closest to the goal
+#[test]
+fn test_anytime_partial_plan() {
+    let (start, actions, goal) = endless_search();
+
+    let options = PlanOptions::new().with_max_expanded_nodes(100);
+    let plan = make_plan_anytime(&start, &actions[..], &goal, &options).unwrap();
+    assert!(plan.is_partial());
+    assert_eq!(Some(BudgetLimit::ExpandedNodes(100)), plan.partial);
+    // 6 and 8 are both one away from 7, but 6 is cheaper to reach
+    assert_eq!(Cost::from(3), plan.cost);
+    let effects: Vec<Effect> = get_effects_from_plan(plan).collect();
+    assert_eq!(3, effects.len());
+    assert_eq!(
+        Some(&Datum::I64(6)),
+        effects.last().unwrap().state.get("energy")
+    );
+}
+
+#[test]
+fn test_anytime_complete_plan() {
+    let start = LocalState::new().with_datum("energy", 0_i64);
+    let goal = Goal::new().with_req("energy", Compare::equals(6_i64));
+    let actions = [simple_increment_action("rest", "energy", 2_i64)];
+
+    let options = PlanOptions::new().with_max_expanded_nodes(100);
+    let plan = make_plan_anytime(&start, &actions[..], &goal, &options).unwrap();
+    assert!(!plan.is_partial());
+    assert_eq!(Ok(plan), make_plan(&start, &actions[..], &goal));
+
+    assert_admissible(&start, &actions[..], &goal);
+}
+
+// Without getting any closer to the goal, there is no partial plan to return
+#[test]
+fn test_anytime_without_progress() {
+    let (start, actions, goal) = endless_search();
+
+    for options in [
+        PlanOptions::new().with_max_expanded_nodes(0),
+        PlanOptions::new()
+            .with_strategy(PlanningStrategy::GoalToStart)
+            .with_max_expanded_nodes(100),
+    ] {
+        let plan = make_plan_anytime(&start, &actions[..], &goal, &options);
+        assert!(
+            matches!(plan, Err(PlanError::BudgetExceeded(_))),
+            "{options:?}: {plan:?}"
+        );
+    }
+}
+
+// Gold can be robbed in small or big amounts, and both are needed before we can retire
+fn heuristic_scenario() -> (LocalState, Vec<Action>, Goal) {
+    let start = LocalState::new()
+        .with_datum("gold", 0_i64)
+        .with_datum("energy", 0_i64)
+        .with_datum("is_retired", false);
+    let goal = Goal::new().with_req("is_retired", Compare::equals(true));
+    let actions = vec![
+        simple_increment_action("pickpocket", "gold", 1_i64),
+        simple_increment_action("rob_bank", "gold", 5_i64).set_cost(4),
+        simple_increment_action("sleep", "energy", 10_i64),
+        simple_action("retire", "is_retired", true)
+            .with_precondition(("gold", Compare::greater_than_equals(12_i64)))
+            .with_precondition(("energy", Compare::greater_than_equals(20_i64))),
+    ];
+    (start, actions, goal)
+}
+
+#[test]
+fn test_admissible_heuristics_find_cheapest_plan() {
+    let (start, actions, goal) = heuristic_scenario();
+
+    let cost = make_plan(&start, &actions[..], &goal).unwrap().cost;
+    // rob_bank twice, pickpocket twice, sleep twice and retire
+    assert_eq!(Cost::from(13), cost);
+
+    for options in [
+        PlanOptions::new().with_heuristic(ZeroHeuristic),
+        PlanOptions::new().with_heuristic(DistanceHeuristic),
+        PlanOptions::new().with_heuristic(HMaxHeuristic),
+    ] {
+        let plan = make_plan_with_options(&start, &actions[..], &goal, &options).unwrap();
+        assert_eq!(cost, plan.cost, "{options:?}");
+    }
+}
+
+#[test]
+fn test_relaxed_heuristics() {
+    let (start, actions, goal) = heuristic_scenario();
+    let context = HeuristicContext::new(&actions, &goal);
+
+    // retire can only follow after 12 gold (at least 9.6 when robbing banks) and 20 energy (2)
+    let h_max = HMaxHeuristic.estimate(&start, &context).value();
+    assert!((h_max - 10.6).abs() < 1e-9, "{h_max}");
+    // Robbing the bank three times, sleeping twice and retiring
+    assert_eq!(Cost::from(15), HAddHeuristic.estimate(&start, &context));
+    assert_eq!(Cost::from(15), FfHeuristic.estimate(&start, &context));
+
+    for options in [
+        PlanOptions::new().with_heuristic(HAddHeuristic),
+        PlanOptions::new().with_heuristic(FfHeuristic),
+    ] {
+        let plan = make_plan_with_options(&start, &actions[..], &goal, &options).unwrap();
+        let effects: Vec<Effect> = get_effects_from_plan(plan).collect();
+        assert_eq!("retire", effects.last().unwrap().action, "{options:?}");
+    }
+}
+
+#[test]
+fn test_relaxed_heuristics_unreachable_goal() {
+    let start = LocalState::new().with_datum("gold", 0_i64);
+    let goal = Goal::new().with_req("gold", Compare::greater_than_equals(5_i64));
+    let actions = [simple_decrement_action("spend", "gold", 1_i64)];
+    let context = HeuristicContext::new(&actions, &goal);
+
+    for heuristic in [
+        &HMaxHeuristic as &dyn Heuristic,
+        &HAddHeuristic,
+        &FfHeuristic,
+    ] {
+        assert_eq!(Cost::INFINITY, heuristic.estimate(&start, &context));
+    }
+
+    let options = PlanOptions::new()
+        .with_heuristic(HMaxHeuristic)
+        .with_max_expanded_nodes(100);
+    assert_eq!(
+        Err(PlanError::BudgetExceeded(BudgetLimit::ExpandedNodes(100))),
+        make_plan_with_options(&start, &actions[..], &goal, &options)
+    );
+}
+
+#[test]
+fn test_weighted_a_star() {
+    let (start, actions, goal) = heuristic_scenario();
+    let cheapest = make_plan(&start, &actions[..], &goal).unwrap().cost;
+
+    for epsilon in [0.0, 0.5, 2.0] {
+        let options =
+            PlanOptions::new().with_heuristic(WeightedAStar::new(DistanceHeuristic, epsilon));
+        let cost = make_plan_with_options(&start, &actions[..], &goal, &options)
+            .unwrap()
+            .cost;
+        assert!(
+            cost <= cheapest * (1.0 + epsilon),
+            "epsilon {epsilon}: {cost} vs {cheapest}"
+        );
+    }
+}
+
+#[test]
+fn test_weighted_distance_heuristic() {
+    let start = LocalState::new()
+        .with_datum("gold", 0_i64)
+        .with_datum("energy", 0_i64);
+    let goal = Goal::new()
+        .with_req("gold", Compare::greater_than_equals(5_i64))
+        .with_req("energy", Compare::greater_than_equals(3_i64));
+    let actions = [
+        simple_increment_action("rob", "gold", 1_i64),
+        simple_increment_action("sleep", "energy", 1_i64),
+    ];
+
+    let heuristic = WeightedDistanceHeuristic::new()
+        .with_weight("gold", 2.0)
+        .with_default_weight(0.5);
+    let context = HeuristicContext::new(&actions, &goal);
+    assert_eq!(Cost::from(11.5), heuristic.estimate(&start, &context));
+
+    let options = PlanOptions::new().with_heuristic(heuristic);
+    let plan = make_plan_with_options(&start, &actions[..], &goal, &options).unwrap();
+    let cost = plan.cost;
+    assert_eq!(Cost::from(8), cost);
+    let effects: Vec<Effect> = get_effects_from_plan(plan).collect();
+    let expected_state = LocalState::new()
+        .with_datum("gold", 5_i64)
+        .with_datum("energy", 3_i64);
+    assert_eq!(expected_state, effects.last().unwrap().state);
+}
+
+// Counts the requirements that aren't met yet, which is all it takes to implement a heuristic
+#[derive(Debug)]
+struct UnmetRequirements;
+
+impl Heuristic for UnmetRequirements {
+    fn estimate(&self, state: &LocalState, context: &HeuristicContext) -> Cost {
+        context
+            .requirement_costs(state)
+            .filter(|(_, cost)| *cost > Cost::ZERO)
+            .count()
+            .into()
+    }
+}
+
+#[test]
+fn test_custom_heuristic() {
+    let (start, actions, goal) = heuristic_scenario();
+
+    let options = PlanOptions::new().with_heuristic(UnmetRequirements);
+    let plan = make_plan_with_options(&start, &actions[..], &goal, &options).unwrap();
+    let effects: Vec<Effect> = get_effects_from_plan(plan).collect();
+    assert_eq!("retire", effects.last().unwrap().action);
+}
+
+#[test]
+fn test_compiled_domain() {
+    let actions = vec![
+        simple_increment_action("work", "gold", 1_i64),
+        simple_decrement_action("eat", "hunger", 1_i64)
+            .with_precondition(("gold", Compare::greater_than_equals(1_i64))),
+    ];
+    let goal = Goal::new().with_req("gold", Compare::greater_than_equals(3_i64));
+    let domain = CompiledDomain::new(&actions, &goal);
+    assert_eq!(Some(0), domain.slot("gold"));
+    assert_eq!(Some(1), domain.slot("hunger"));
+    assert_eq!(None, domain.slot("energy"));
+
+    // The same domain can plan from any start state
+    let options = PlanOptions::new();
+    for gold in 0..3_i64 {
+        let start = LocalState::new()
+            .with_datum("gold", gold)
+            .with_datum("hunger", 5_i64)
+            .with_datum("energy", 10_i64);
+        let plan = domain.make_plan(&start, &options).unwrap();
+        assert_eq!(
+            make_plan_with_options(&start, &actions, &goal, &options).unwrap(),
+            plan
+        );
+        let effects: Vec<Effect> = get_effects_from_plan(plan).collect();
+        assert_eq!(3 - gold, effects.len() as i64);
+        // Keys without a slot are carried along untouched
+        let last = &effects.last().unwrap().state;
+        assert_eq!(Some(&Datum::I64(10)), last.get("energy"));
+    }
+
+    let missing = LocalState::new().with_datum("hunger", 5_i64);
+    assert_eq!(
+        Err(PlanError::MissingKey("gold".into())),
+        domain.make_plan(&missing, &options)
+    );
+}
+
+#[test]
+fn test_planning_context() {
+    let mut context = PlanningContext::new();
+    let start = LocalState::new()
+        .with_datum("gold", 0_i64)
+        .with_datum("energy", 0_i64);
+    let gold = CompiledDomain::new(
+        &[simple_increment_action("work", "gold", 1_i64)],
+        &Goal::new().with_req("gold", Compare::equals(3_i64)),
+    );
+    let energy = CompiledDomain::new(
+        &[
+            simple_increment_action("sleep", "energy", 1_i64),
+            simple_increment_action("work", "gold", 1_i64)
+                .with_precondition(("energy", Compare::greater_than_equals(1_i64))),
+        ],
+        &Goal::new().with_req("gold", Compare::equals(2_i64)),
+    );
+
+    // The same context can be used for any domain and strategy, one plan after the other
+    for _ in 0..2 {
+        for domain in [&gold, &energy] {
+            for strategy in [PlanningStrategy::StartToGoal, PlanningStrategy::GoalToStart] {
+                let options = PlanOptions::new().with_strategy(strategy);
+                assert_eq!(
+                    domain.make_plan(&start, &options),
+                    domain.make_plan_with_context(&start, &options, &mut context)
+                );
+            }
+        }
+    }
+
+    let options = PlanOptions::new().with_max_expanded_nodes(1);
+    assert_eq!(
+        gold.make_plan_anytime(&start, &options),
+        gold.make_plan_anytime_with_context(&start, &options, &mut context)
+    );
+}
+
+#[test]
+fn test_incremental_planner() {
+    let actions = vec![
+        simple_increment_action("work", "gold", 1_i64).set_cost(3),
+        simple_increment_action("rob", "gold", 2_i64)
+            .set_cost(5)
+            .with_precondition(("energy", Compare::greater_than_equals(1_i64))),
+        simple_increment_action("sleep", "energy", 1_i64),
+    ];
+    let goal = Goal::new().with_req("gold", Compare::greater_than_equals(4_i64));
+    let mut planner = IncrementalPlanner::new(CompiledDomain::new(&actions, &goal));
+    let options = PlanOptions::new();
+    let start = LocalState::new()
+        .with_datum("gold", 0_i64)
+        .with_datum("energy", 0_i64);
+
+    assert_eq!(
+        make_plan(&start, &actions, &goal),
+        planner.make_plan(&start, &options)
+    );
+
+    // A new start keeps the costs found so far, only the estimates change. Robbing or working
+    // first costs the same, so only the cost is compared.
+    let moved = LocalState::new()
+        .with_datum("gold", 1_i64)
+        .with_datum("energy", 1_i64);
+    let plan = planner.make_plan(&moved, &options).unwrap();
+    let cost = plan.cost;
+    assert_eq!(make_plan(&moved, &actions, &goal).unwrap().cost, cost);
+    assert!(simulate_plan(&moved, &plan.steps, &actions).is_ok());
+
+    // Cheaper robbing changes the plan
+    planner.set_action_cost("rob", 1);
+    let cheaper_rob = vec![
+        actions[0].clone(),
+        actions[1].clone().set_cost(1),
+        actions[2].clone(),
+    ];
+    let plan = planner.make_plan(&moved, &options).unwrap();
+    let cost = plan.cost;
+    assert_eq!(make_plan(&moved, &cheaper_rob, &goal).unwrap().cost, cost);
+    let effects: Vec<Effect> = get_effects_from_plan(plan).collect();
+    assert!(effects.iter().all(|effect| effect.action != "work"));
+    assert_eq!(
+        Cost::ONE,
+        effects.iter().find(|e| e.action == "rob").unwrap().cost
+    );
+
+    // And more expensive robbing changes it back
+    planner.set_action_cost("rob", 20);
+    let cost = planner.make_plan(&moved, &options).unwrap().cost;
+    assert_eq!(Cost::from(9), cost);
+
+    let done = LocalState::new()
+        .with_datum("gold", 4_i64)
+        .with_datum("energy", 0_i64);
+    assert_eq!(
+        Err(PlanError::GoalAlreadySatisfied),
+        planner.make_plan(&done, &options)
+    );
+
+    // Sleeping doesn't get any gold, which searching back from the goal sees right away
+    let mut impossible = IncrementalPlanner::new(CompiledDomain::new(
+        &actions[2..],
+        &Goal::new().with_req("gold", Compare::equals(1_i64)),
+    ));
+    assert_eq!(
+        Err(PlanError::SearchExhausted),
+        impossible.make_plan(&start, &options)
+    );
+
+    // But there's always more gold to go back to before gambling
+    let mut endless = IncrementalPlanner::new(CompiledDomain::new(
+        &[simple_increment_action("gamble", "gold", 2_i64)],
+        &Goal::new().with_req("gold", Compare::equals(1_i64)),
+    ));
+    let limited = PlanOptions::new().with_max_expanded_nodes(20);
+    assert_eq!(
+        Err(PlanError::BudgetExceeded(BudgetLimit::ExpandedNodes(20))),
+        endless.make_plan(&start, &limited)
+    );
+}
+
+#[test]
+fn test_incremental_planner_moving_start() {
+    let actions = vec![
+        simple_increment_action("work", "gold", 1_i64),
+        simple_increment_action("rob", "gold", 3_i64)
+            .set_cost(2)
+            .with_precondition(("energy", Compare::greater_than_equals(1_i64))),
+        simple_decrement_action("spend_energy", "energy", 1_i64),
+        simple_increment_action("sleep", "energy", 1_i64).set_cost(2),
+    ];
+    let goal = Goal::new().with_req("gold", Compare::greater_than_equals(10_i64));
+    let domain = CompiledDomain::new(&actions, &goal);
+    let mut planner = IncrementalPlanner::new(domain.clone());
+    let mut state = LocalState::new()
+        .with_datum("gold", 0_i64)
+        .with_datum("energy", 0_i64);
+
+    let options = PlanOptions::new();
+    let mut plan = planner.make_plan(&state, &options).unwrap();
+    let states = planner.states();
+
+    // Following the plan, the search for the first plan already knows what's left, so hardly
+    // anything has to be expanded, while planning from scratch runs out
+    let limited = PlanOptions::new().with_max_expanded_nodes(3);
+    assert!(domain.make_plan(&plan.steps[0].state, &limited).is_err());
+    while let Some(step) = plan.steps.first() {
+        state = step.state.clone();
+        match planner.make_plan(&state, &limited) {
+            Ok(next) => plan = next,
+            Err(PlanError::GoalAlreadySatisfied) => break,
+            Err(err) => panic!("{err}"),
+        }
+        assert_eq!(domain.make_plan(&state, &options).unwrap().cost, plan.cost);
+    }
+    assert_eq!(states, planner.states());
+
+    // Once it has seen too many states, it gives up, and the next plan starts over
+    let start = LocalState::new()
+        .with_datum("gold", 0_i64)
+        .with_datum("energy", 0_i64);
+    let mut small = IncrementalPlanner::new(domain.clone()).with_max_states(5);
+    assert_eq!(
+        Err(PlanError::BudgetExceeded(BudgetLimit::States(5))),
+        small.make_plan(&start, &options)
+    );
+    assert!(small.states() > 5);
+    let restarted = small.make_plan(&state.with_datum("gold", 9_i64), &options);
+    assert_eq!(Cost::ONE, restarted.unwrap().cost);
+    assert!(small.states() <= 5);
+
+    // Room for as many states as the first plan needed is enough
+    let mut roomy = IncrementalPlanner::new(domain.clone()).with_max_states(states);
+    let cost = domain.make_plan(&start, &options).unwrap().cost;
+    assert_eq!(cost, roomy.make_plan(&start, &options).unwrap().cost);
+}
+
+#[test]
+fn test_repair_plan() {
+    let actions = vec![
+        simple_action("get_axe", "has_axe", true).set_cost(2),
+        simple_action("chop", "has_wood", true)
+            .with_precondition(("has_axe", Compare::equals(true))),
+        simple_action("build", "has_house", true)
+            .with_precondition(("has_wood", Compare::equals(true))),
+    ];
+    let goal = Goal::new().with_req("has_house", Compare::equals(true));
+    let start = LocalState::new()
+        .with_datum("has_axe", false)
+        .with_datum("has_wood", false)
+        .with_datum("has_house", false);
+    let plan = make_plan(&start, &actions, &goal).unwrap();
+    let effects: Vec<Effect> = get_effects_from_plan(plan).collect();
+    let steps = |plan: Plan| -> Vec<String> {
+        get_effects_from_plan(plan)
+            .map(|effect| effect.action)
+            .collect()
+    };
+
+    // Nothing changed after getting the axe, so the rest of the plan is kept
+    let plan = repair_plan(&effects[0].state, &effects[1..], &actions, &goal).unwrap();
+    let cost = plan.cost;
+    assert_eq!(vec!["chop", "build"], steps(plan));
+    assert_eq!(Cost::from(2), cost);
+
+    // The axe got lost, so it has to be fetched again
+    let lost_axe = effects[0].state.clone().with_datum("has_axe", false);
+    let plan = repair_plan(&lost_axe, &effects[1..], &actions, &goal).unwrap();
+    let cost = plan.cost;
+    assert_eq!(vec!["get_axe", "chop", "build"], steps(plan));
+    assert_eq!(Cost::from(4), cost);
+
+    // Someone took the axe but left wood, so the plan picks up from building
+    let swapped = lost_axe.with_datum("has_wood", true);
+    let plan = repair_plan(&swapped, &effects[1..], &actions, &goal).unwrap();
+    let cost = plan.cost;
+    assert_eq!(vec!["build"], steps(plan));
+    assert_eq!(Cost::from(1), cost);
+
+    // Steps of actions that don't exist anymore can't be kept
+    let renamed = vec![
+        actions[0].clone(),
+        actions[1].clone(),
+        simple_action("construct", "has_house", true),
+    ];
+    let plan = repair_plan(&effects[1].state, &effects[2..], &renamed, &goal).unwrap();
+    assert_eq!(vec!["construct"], steps(plan));
+
+    assert_eq!(
+        Err(PlanError::GoalAlreadySatisfied),
+        repair_plan(&effects[2].state, &[], &actions, &goal)
+    );
+}
+
+// Rules out every state where the axe is at hand
+#[derive(Debug)]
+struct AvoidAxe;
+
+impl Heuristic for AvoidAxe {
+    fn estimate(&self, state: &LocalState, _context: &HeuristicContext) -> Cost {
+        if state.get("has_axe") == Some(&Datum::Bool(true)) {
+            Cost::INFINITY
+        } else {
+            Cost::ZERO
+        }
+    }
+}
+
+#[test]
+fn test_repair_plan_with_options() {
+    let actions = vec![
+        simple_action("get_axe", "has_axe", true).set_cost(2),
+        simple_action("chop", "has_wood", true)
+            .with_precondition(("has_axe", Compare::equals(true))),
+        simple_action("buy_wood", "has_wood", true).set_cost(5),
+        simple_action("build", "has_house", true)
+            .with_precondition(("has_wood", Compare::equals(true))),
+    ];
+    let goal = Goal::new().with_req("has_house", Compare::equals(true));
+    let start = LocalState::new()
+        .with_datum("has_axe", false)
+        .with_datum("has_wood", false)
+        .with_datum("has_house", false);
+    let plan = make_plan(&start, &actions, &goal).unwrap();
+    assert_eq!(
+        vec!["get_axe", "chop", "build"],
+        plan.actions().collect::<Vec<_>>()
+    );
+    let lost_axe = plan.steps[0].state.clone().with_datum("has_axe", false);
+    let remaining = &plan.steps[1..];
+
+    let repaired =
+        repair_plan_with_options(&lost_axe, remaining, &actions, &goal, &PlanOptions::new())
+            .unwrap();
+    assert_eq!(
+        vec!["get_axe", "chop", "build"],
+        repaired.actions().collect::<Vec<_>>()
+    );
+
+    // The limits count the kept steps too, not just the ones found to get back to them
+    assert_eq!(
+        Err(PlanError::BudgetExceeded(BudgetLimit::PlanLength(2))),
+        repair_plan_with_options(
+            &lost_axe,
+            remaining,
+            &actions,
+            &goal,
+            &PlanOptions::new().with_max_plan_length(2)
+        )
+    );
+    assert_eq!(
+        Err(PlanError::BudgetExceeded(BudgetLimit::Cost(Cost::from(3)))),
+        repair_plan_with_options(
+            &lost_axe,
+            remaining,
+            &actions,
+            &goal,
+            &PlanOptions::new().with_max_cost(3)
+        )
+    );
+
+    // The heuristic guides the search, here away from fetching the axe again
+    let options = PlanOptions::new().with_heuristic(AvoidAxe);
+    let repaired =
+        repair_plan_with_options(&lost_axe, remaining, &actions, &goal, &options).unwrap();
+    let cost = repaired.cost;
+    assert_eq!(
+        vec!["buy_wood", "build"],
+        repaired.actions().collect::<Vec<_>>()
+    );
+    assert_eq!(Cost::from(6), cost);
+
+    // A compiled domain repairs the same, also in a context that planned before
+    let domain = CompiledDomain::new(&actions, &goal);
+    let mut context = PlanningContext::new();
+    domain
+        .make_plan_with_context(&start, &PlanOptions::new(), &mut context)
+        .unwrap();
+    assert_eq!(
+        Ok(repaired),
+        domain.repair_plan_with_context(&lost_axe, remaining, &options, &mut context)
+    );
+    assert_eq!(
+        repair_plan(&lost_axe, remaining, &actions, &goal),
+        domain.repair_plan(&lost_axe, remaining, &PlanOptions::new())
+    );
+}
+
+#[test]
+fn test_simulate_plan() {
+    let actions = vec![
+        simple_action("get_axe", "has_axe", true),
+        simple_action("chop", "has_wood", true)
+            .with_precondition(("has_axe", Compare::equals(true))),
+    ];
+    let start = LocalState::new()
+        .with_datum("has_axe", false)
+        .with_datum("has_wood", false);
+    let goal = Goal::new().with_req("has_wood", Compare::equals(true));
+    let plan = make_plan(&start, &actions, &goal).unwrap();
+    let effects: Vec<Effect> = get_effects_from_plan(plan).collect();
+
+    assert_eq!(
+        Ok(effects[1].state.clone()),
+        simulate_plan(&start, &effects, &actions)
+    );
+    assert_eq!(Ok(start.clone()), simulate_plan(&start, &[], &actions));
+
+    assert_eq!(
+        Err(SimulationError {
+            step: 0,
+            reason: StepFailure::PreconditionFailed("has_axe".into(), Compare::equals(true)),
+        }),
+        simulate_plan(&start, &effects[1..], &actions)
+    );
+    assert_eq!(
+        Err(SimulationError {
+            step: 1,
+            reason: StepFailure::UnknownAction("chop".to_string()),
+        }),
+        simulate_plan(&start, &effects, &actions[..1])
+    );
+    let broken = start.clone().with_datum("has_axe", 1_i64);
+    assert!(matches!(
+        simulate_plan(&broken, &effects[1..], &actions),
+        Err(SimulationError {
+            step: 0,
+            reason: StepFailure::Error(PlanError::TypeMismatch { .. }),
+        })
+    ));
+}
+
+#[test]
+fn test_plan() {
+    let actions = vec![
+        simple_action("get_axe", "has_axe", true).set_cost(2),
+        simple_action("chop", "has_wood", true)
+            .with_precondition(("has_axe", Compare::equals(true))),
+    ];
+    let start = LocalState::new()
+        .with_datum("has_axe", false)
+        .with_datum("has_wood", false);
+    let goal = Goal::new().with_req("has_wood", Compare::equals(true));
+    let plan = make_plan(&start, &actions, &goal).unwrap();
+
+    assert_eq!(start, plan.start);
+    assert_eq!(goal, plan.goal);
+    assert_eq!(Cost::from(3), plan.cost);
+    assert_eq!(2, plan.len());
+    assert!(!plan.is_partial());
+    assert_eq!(vec!["get_axe", "chop"], plan.actions().collect::<Vec<_>>());
+    assert_eq!(
+        vec![2.0, 1.0],
+        plan.iter()
+            .map(|step| step.cost.value())
+            .collect::<Vec<_>>()
+    );
+    let states: Vec<&LocalState> = plan.states().collect();
+    assert_eq!(3, states.len());
+    assert_eq!(&start, states[0]);
+    assert_eq!(&start.clone().with_datum("has_axe", true), states[1]);
+    assert_eq!(
+        &LocalState::new()
+            .with_datum("has_axe", true)
+            .with_datum("has_wood", true),
+        plan.final_state()
+    );
+
+    let formatted = format_plan(plan.clone());
+    assert_eq!(plan.to_string(), formatted);
+    assert!(formatted.contains("= DO ACTION \"chop\""));
+    assert!(formatted.contains("= FINAL STATE (COST: 3)"));
+
+    let effects: Vec<Effect> = get_effects_from_plan(plan.clone()).collect();
+    assert_eq!(plan.steps, effects);
+}
+
+#[test]
+fn test_make_plans() {
+    let actions = vec![
+        simple_increment_action("work", "gold", 1_i64),
+        simple_increment_action("rob", "gold", 2_i64).set_cost(3),
+    ];
+    let goal = Goal::new().with_req("gold", Compare::greater_than_equals(2_i64));
+    let start = LocalState::new().with_datum("gold", 0_i64);
+
+    let plans = make_plans(&start, &actions, &goal, 10).unwrap();
+    let sequences: Vec<Vec<&str>> = plans.iter().map(|plan| plan.actions().collect()).collect();
+    assert_eq!(
+        vec![vec!["work", "work"], vec!["rob"], vec!["work", "rob"]],
+        sequences
+    );
+    assert_eq!(
+        vec![2.0, 3.0, 4.0],
+        plans
+            .iter()
+            .map(|plan| plan.cost.value())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(make_plan(&start, &actions, &goal).unwrap(), plans[0]);
+
+    assert_eq!(plans[..2], make_plans(&start, &actions, &goal, 2).unwrap());
+    assert!(make_plans(&start, &actions, &goal, 0).unwrap().is_empty());
+    assert_eq!(
+        Err(PlanError::GoalAlreadySatisfied),
+        make_plans(
+            &LocalState::new().with_datum("gold", 2_i64),
+            &actions,
+            &goal,
+            3
+        )
+    );
+    assert_eq!(
+        Err(PlanError::SearchExhausted),
+        make_plans(&start, &actions[..0], &goal, 3)
+    );
+}
+
+#[test]
+fn test_seeded_tie_breaking() {
+    let actions = vec![
+        simple_action("wash", "is_clean", true),
+        simple_action("eat", "is_fed", true),
+    ];
+    let start = LocalState::new()
+        .with_datum("is_clean", false)
+        .with_datum("is_fed", false);
+    let goal = Goal::new()
+        .with_req("is_clean", Compare::equals(true))
+        .with_req("is_fed", Compare::equals(true));
+    let first_action = |options: &PlanOptions| {
+        let plan = make_plan_with_options(&start, &actions, &goal, options).unwrap();
+        assert_eq!(Cost::from(2), plan.cost);
+        plan.steps[0].action.clone()
+    };
+
+    // Without a seed, the same plan wins every time
+    let unseeded = first_action(&PlanOptions::new());
+    for _ in 0..8 {
+        assert_eq!(unseeded, first_action(&PlanOptions::new()));
+    }
+
+    // The same seed picks the same plan, but different seeds pick different ones
+    let mut picked = Vec::new();
+    for seed in 0..32 {
+        let options = PlanOptions::new().with_seed(seed);
+        let action = first_action(&options);
+        assert_eq!(action, first_action(&options));
+        picked.push(action);
+    }
+    assert!(picked.contains(&"wash".to_string()));
+    assert!(picked.contains(&"eat".to_string()));
+}
+
+// Costs can be fractional, like the distance walked
+// Two small steps are cheaper than one big one, even though the gap is less than 1
+#[test]
+fn test_fractional_steps() {
+    let start = LocalState::new().with_datum("x", 0.0);
+    let goal = Goal::new().with_req("x", Compare::greater_than_equals(0.2));
+    let actions = vec![
+        simple_increment_action("small", "x", 0.1).set_cost(1),
+        simple_increment_action("pricey", "x", 0.2).set_cost(2.5),
+    ];
+    let plan = make_plan(&start, &actions, &goal).unwrap();
+    let cost = plan.cost;
+    assert_eq!(vec!["small", "small"], plan.actions().collect::<Vec<_>>());
+    assert_eq!(Cost::from(2), cost);
+    assert!(estimate_remaining_cost(&start, &actions, &goal) <= cost);
+    assert_admissible(&start, &actions, &goal);
+}
+
+#[test]
+fn test_fractional_costs() {
+    let start = LocalState::new().with_datum("distance", 0.0);
+    let goal = Goal::new().with_req("distance", Compare::greater_than_equals(3.0));
+    let actions = vec![
+        simple_increment_action("walk", "distance", 1.0).set_cost(1.5),
+        simple_increment_action("run", "distance", 2.0).set_cost(2.25_f32),
+    ];
+
+    for options in [
+        PlanOptions::new(),
+        PlanOptions::new().with_heuristic(HMaxHeuristic),
+        PlanOptions::new().with_heuristic(FfHeuristic),
+        PlanOptions::new().with_strategy(PlanningStrategy::GoalToStart),
+    ] {
+        let plan = make_plan_with_options(&start, &actions, &goal, &options).unwrap();
+        assert_eq!(Cost::from(3.75), plan.cost, "{options:?}");
+        let mut actions_taken: Vec<&str> = plan.actions().collect();
+        actions_taken.sort_unstable();
+        assert_eq!(vec!["run", "walk"], actions_taken, "{options:?}");
+    }
+
+    let options = PlanOptions::new().with_max_cost(3.5);
+    assert_eq!(
+        Err(PlanError::BudgetExceeded(BudgetLimit::Cost(Cost::from(
+            3.5
+        )))),
+        make_plan_with_options(&start, &actions, &goal, &options)
+    );
+    assert_admissible(&start, &actions, &goal);
+
+    // Two cheap actions beat one that does both, even though each of them costs less than 1
+    let start = LocalState::new()
+        .with_datum("fed", false)
+        .with_datum("rested", false);
+    let goal = Goal::new()
+        .with_req("fed", Compare::equals(true))
+        .with_req("rested", Compare::equals(true));
+    let actions = vec![
+        simple_multi_mutate_action("picnic", [("fed", true), ("rested", true)]).set_cost(1.5),
+        simple_action("eat", "fed", true).set_cost(0.6),
+        simple_action("nap", "rested", true).set_cost(0.6),
+    ];
+    let options = PlanOptions::new().with_strategy(PlanningStrategy::GoalToStart);
+    let plan = make_plan_with_options(&start, &actions, &goal, &options).unwrap();
+    assert_eq!(Cost::from(1.2), plan.cost);
+}
+
+// Walking costs more the farther away the ore is, so teleporting is cheaper when it's far
+#[test]
+fn test_state_dependent_costs() {
+    let actions = vec![
+        simple_action("walk", "at_ore", true).set_cost_fn(|state: &LocalState| {
+            match state.data.get(&Key::from("distance")) {
+                Some(Datum::I64(distance)) => *distance as f64,
+                _ => f64::INFINITY,
+            }
+        }),
+        simple_action("teleport", "at_ore", true).set_cost(5),
+    ];
+    let goal = Goal::new().with_req("at_ore", Compare::equals(true));
+    let domain = CompiledDomain::new(&actions, &goal);
+    let mut incremental = IncrementalPlanner::new(domain.clone());
+
+    for (distance, expected, cost) in [(2_i64, "walk", 2), (10, "teleport", 5)] {
+        let start = LocalState::new()
+            .with_datum("at_ore", false)
+            .with_datum("distance", distance);
+        let plans = [
+            make_plan(&start, &actions, &goal).unwrap(),
+            domain.make_plan(&start, &PlanOptions::new()).unwrap(),
+            incremental.make_plan(&start, &PlanOptions::new()).unwrap(),
+            make_plans(&start, &actions, &goal, 2).unwrap().remove(0),
+        ];
+        for plan in plans {
+            assert_eq!(vec![expected], plan.actions().collect::<Vec<_>>());
+            assert_eq!(Cost::from(cost), plan.cost);
+        }
+    }
+
+    // The cost of each step is the one in the state it was taken from
+    let start = LocalState::new()
+        .with_datum("at_ore", false)
+        .with_datum("distance", 3_i64);
+    let options = PlanOptions::new().with_strategy(PlanningStrategy::GoalToStart);
+    let plan = make_plan_with_options(&start, &actions, &goal, &options).unwrap();
+    assert_eq!(Cost::from(3), plan.cost);
+    assert_eq!(Cost::from(3), plan.steps[0].cost);
+
+    // Regressing only knows the static cost, which is 0 for walking, so it plans from the start
+    let far = start.clone().with_datum("distance", 10_i64);
+    let plan = make_plan_with_options(&far, &actions, &goal, &options).unwrap();
+    assert_eq!(vec!["teleport"], plan.actions().collect::<Vec<_>>());
+    let options = options.with_max_cost(6);
+    assert_eq!(
+        Cost::from(5),
+        make_plan_with_options(&far, &actions, &goal, &options)
+            .unwrap()
+            .cost
+    );
+
+    // A cost that isn't a number is infinite, instead of panicking in the middle of the search
+    let broken = actions[0].clone().set_cost_fn(|_: &LocalState| f64::NAN);
+    let plan = make_plan(&start, &[broken, actions[1].clone()], &goal).unwrap();
+    assert_eq!(vec!["teleport"], plan.actions().collect::<Vec<_>>());
+    assert_eq!(Cost::INFINITY, Cost::INFINITY - Cost::INFINITY);
+
+    // A static cost set afterwards is only the least it can cost
+    let walk = actions[0].clone().set_cost(1);
+    assert_eq!(Cost::from(1), walk.effects[0].cost);
+    assert_eq!(
+        Cost::from(3),
+        make_plan(&start, &[walk], &goal).unwrap().cost
+    );
+}
+
+// Mining needs a reachable ore node, which a closure finds in the state
+#[test]
+fn test_precondition_fns() {
+    let reachable_ore = |state: &LocalState| matches!(state.data.get(&Key::from("ore_distance")), Some(Datum::I64(distance)) if *distance <= 3);
+    let actions = vec![
+        simple_action("mine", "has_ore", true).with_precondition_fn("reachable_ore", reachable_ore),
+        simple_decrement_action("walk", "ore_distance", 5_i64),
+    ];
+    let goal = Goal::new().with_req("has_ore", Compare::equals(true));
+    let start = LocalState::new()
+        .with_datum("has_ore", false)
+        .with_datum("ore_distance", 8_i64);
+
+    for strategy in [PlanningStrategy::StartToGoal, PlanningStrategy::GoalToStart] {
+        let options = PlanOptions::new().with_strategy(strategy);
+        let plan = make_plan_with_options(&start, &actions, &goal, &options).unwrap();
+        assert_eq!(
+            vec!["walk", "mine"],
+            plan.actions().collect::<Vec<_>>(),
+            "{strategy:?}"
+        );
+    }
+
+    let close = start.clone().with_datum("ore_distance", 2_i64);
+    let plan = make_plan(&close, &actions, &goal).unwrap();
+    assert_eq!(vec!["mine"], plan.actions().collect::<Vec<_>>());
+    assert_eq!(
+        Err(SimulationError {
+            step: 0,
+            reason: StepFailure::PreconditionFnFailed("reachable_ore".to_string()),
+        }),
+        simulate_plan(&start, &plan.steps, &actions)
+    );
+
+    // Actions with them can still be cloned, compared and hashed
+    let mine = actions[0].clone();
+    assert_eq!(actions[0], mine);
+    assert_eq!(
+        "[PreconditionFn(\"reachable_ore\")]",
+        format!("{:?}", mine.precondition_fns)
+    );
+    let hash = |action: &Action| {
+        let mut hasher = DefaultHasher::new();
+        action.hash(&mut hasher);
+        hasher.finish()
+    };
+    assert_eq!(hash(&actions[0]), hash(&mine));
+}
+
+#[test]
+fn test_conditions() {
+    let can_mine = Condition::any([
+        ("has_axe", Compare::equals(true)),
+        ("has_pickaxe", Compare::equals(true)),
+    ]);
+    let actions = vec![
+        simple_action("get_axe", "has_axe", true).set_cost(5),
+        simple_action("get_pickaxe", "has_pickaxe", true).set_cost(2),
+        simple_action("mine", "has_ore", true).with_condition(can_mine.clone()),
+    ];
+    let start = LocalState::new()
+        .with_datum("has_axe", false)
+        .with_datum("has_pickaxe", false)
+        .with_datum("has_ore", false);
+    let goal = Goal::new()
+        .with_req("has_ore", Compare::equals(true))
+        .with_condition(!Condition::compare("has_axe", Compare::equals(true)));
+
+    let context = HeuristicContext::new(&actions, &goal);
+    assert_eq!(Cost::from(2), context.condition_cost(&start, &can_mine));
+    assert_eq!(Cost::from(3), HMaxHeuristic.estimate(&start, &context));
+    assert_eq!(Cost::from(3), HAddHeuristic.estimate(&start, &context));
+    assert_eq!(Cost::from(3), FfHeuristic.estimate(&start, &context));
+
+    for options in [
+        PlanOptions::new(),
+        PlanOptions::new().with_heuristic(HMaxHeuristic),
+        PlanOptions::new().with_heuristic(FfHeuristic),
+        // Falls back to searching from the start
+        PlanOptions::new().with_strategy(PlanningStrategy::GoalToStart),
+    ] {
+        let plan = make_plan_with_options(&start, &actions, &goal, &options).unwrap();
+        assert_eq!(
+            vec!["get_pickaxe", "mine"],
+            plan.actions().collect::<Vec<_>>(),
+            "{options:?}"
+        );
+    }
+
+    let plan = make_plan(&start, &actions, &goal).unwrap();
+    assert_eq!(
+        Err(SimulationError {
+            step: 0,
+            reason: StepFailure::ConditionFailed(can_mine),
+        }),
+        simulate_plan(&start, &plan.steps[1..], &actions)
+    );
+}
+
+#[test]
+fn test_strict_ranges_and_sets() {
+    let actions = [
+        simple_action("go_home", "at_location", Datum::Enum(0)),
+        simple_action("go_mine", "at_location", Datum::Enum(1)).set_cost(3),
+        simple_action("go_forest", "at_location", Datum::Enum(2)).set_cost(2),
+        simple_increment_action("dig", "gold", 3_i64),
+    ];
+    let start = LocalState::new()
+        .with_datum("at_location", Datum::Enum(0))
+        .with_datum("gold", 0_i64);
+    for strategy in [PlanningStrategy::StartToGoal, PlanningStrategy::GoalToStart] {
+        let options = PlanOptions::new().with_strategy(strategy);
+        // Anywhere but home
+        let goal = Goal::new().with_req(
+            "at_location",
+            Compare::one_of([Datum::Enum(1), Datum::Enum(2)]),
+        );
+        let plan = make_plan_with_options(&start, &actions, &goal, &options).unwrap();
+        assert_eq!(vec!["go_forest"], plan.actions().collect::<Vec<_>>());
+
+        // 9 isn't enough, so it takes a fourth dig
+        for compare in [
+            Compare::greater_than(9_i64),
+            Compare::in_range(10_i64, 12_i64),
+        ] {
+            let goal = Goal::new().with_req("gold", compare.clone());
+            let plan = make_plan_with_options(&start, &actions, &goal, &options).unwrap();
+            assert_eq!(4, plan.len(), "{strategy:?} {compare:?}");
+        }
+    }
+
+    // Not being in the range is exactly being below or above it
+    let goal =
+        Goal::new().with_condition(!Condition::compare("gold", Compare::in_range(0_i64, 5_i64)));
+    let context = HeuristicContext::new(&actions, &goal);
+    assert_eq!(Cost::from(2), HMaxHeuristic.estimate(&start, &context));
+    let plan = make_plan_with_options(
+        &start,
+        &actions,
+        &goal,
+        &PlanOptions::new().with_heuristic(HMaxHeuristic),
+    )
+    .unwrap();
+    assert_eq!(Cost::from(2), plan.cost);
+}
+
+#[test]
+fn test_compare_to_key() {
+    let actions = vec![
+        simple_increment_action("work", "gold", 2_i64),
+        simple_decrement_action("haggle", "price", 1_i64).set_cost(2),
+        simple_action("buy", "has_sword", true)
+            .with_precondition(("gold", Compare::greater_than_equals_key("price"))),
+    ];
+    let start = LocalState::new()
+        .with_datum("gold", 2_i64)
+        .with_datum("price", 5_i64)
+        .with_datum("has_sword", false);
+    let goal = Goal::new().with_req("has_sword", Compare::equals(true));
+
+    for strategy in [PlanningStrategy::StartToGoal, PlanningStrategy::GoalToStart] {
+        let options = PlanOptions::new().with_strategy(strategy);
+        let plan = make_plan_with_options(&start, &actions, &goal, &options).unwrap();
+        assert_eq!(
+            vec!["work", "work", "buy"],
+            plan.actions().collect::<Vec<_>>(),
+            "{strategy:?}"
+        );
+    }
+    assert_admissible(&start, &actions, &goal);
+
+    let plan = make_plan(&start, &actions, &goal).unwrap();
+    assert_eq!(
+        Err(SimulationError {
+            step: 0,
+            reason: StepFailure::PreconditionFailed(
+                "gold".into(),
+                Compare::greater_than_equals_key("price")
+            ),
+        }),
+        simulate_plan(&start, &plan.steps[2..], &actions)
+    );
+
+    // Both sides of the goal are keys too
+    let goal = Goal::new().with_req("gold", Compare::equals_key("price"));
+    let plan = make_plan(&start, &actions, &goal).unwrap();
+    // Working once and haggling once meets at 4
+    assert_eq!(Cost::from(3), plan.cost);
+    assert_eq!(Some(&Datum::I64(4)), plan.final_state().get("price"));
+    assert!(repair_plan(&start, &plan.steps, &actions, &goal).is_ok());
+}
+
+#[test]
+fn test_more_mutators() {
+    // Investing triples the gold, so working before investing pays off more than after
+    let actions = vec![
+        simple_increment_action("work", "gold", 5_i64),
+        Action::new("invest")
+            .with_mutator(Mutator::multiply("gold", 3_i64))
+            .set_cost(2),
+    ];
+    let start = LocalState::new().with_datum("gold", 10_i64);
+    let goal = Goal::new().with_req("gold", Compare::greater_than_equals(40_i64));
+    // Multiplying can't be regressed, so both strategies search forwards
+    for strategy in [PlanningStrategy::StartToGoal, PlanningStrategy::GoalToStart] {
+        let options = PlanOptions::new().with_strategy(strategy);
+        let plan = make_plan_with_options(&start, &actions, &goal, &options).unwrap();
+        assert_eq!(
+            vec!["work", "invest"],
+            plan.actions().collect::<Vec<_>>(),
+            "{strategy:?}"
+        );
+        assert_eq!(Some(&Datum::I64(45)), plan.final_state().get("gold"));
+    }
+    assert_admissible(&start, &actions, &goal);
+
+    // Depositing adds the gold to the bank, the tax takes a fifth of it but leaves at least 10,
+    // and caps it at 100
+    let actions = vec![
+        Action::new("deposit")
+            .with_mutator(Mutator::add_key("bank", "gold"))
+            .with_mutator(Mutator::set("gold", 0_i64)),
+        Action::new("tax")
+            .with_precondition(("bank", Compare::greater_than(0_i64)))
+            .with_mutator(Mutator::divide("bank", 5_i64))
+            .with_mutator(Mutator::multiply("bank", 4_i64))
+            .with_mutator(Mutator::max("bank", 10_i64))
+            .with_mutator(Mutator::min("bank", 100_i64)),
+        Action::new("open_shop").with_mutator(Mutator::toggle("shop_open")),
+    ];
+    let start = LocalState::new()
+        .with_datum("gold", 150_i64)
+        .with_datum("bank", 0_i64)
+        .with_datum("shop_open", false);
+    let goal = Goal::new()
+        .with_req("bank", Compare::equals(100_i64))
+        .with_req("shop_open", Compare::equals(true));
+    let plan = make_plan(&start, &actions, &goal).unwrap();
+    assert_eq!(Cost::from(3), plan.cost);
+    let state = plan.final_state();
+    assert_eq!(Some(&Datum::I64(0)), state.get("gold"));
+    assert_eq!(Some(&Datum::I64(100)), state.get("bank"));
+    let formatted = format_plan(plan);
+    assert!(formatted.contains("\t\tbank + gold\n"));
+    assert!(formatted.contains("\t\tbank * Datum:I64(4)\n"));
+    assert!(formatted.contains("\t\tbank = min(bank, Datum:I64(100))\n"));
+    assert!(formatted.contains("\t\tshop_open = !shop_open\n"));
+
+    // Toggling, copying and removing can be regressed
+    let actions = vec![
+        Action::new("flip").with_mutator(Mutator::toggle("door_open")),
+        Action::new("quote").with_mutator(Mutator::copy_from("quoted", "price")),
+        Action::new("forget").with_mutator(Mutator::remove("quoted")),
+    ];
+    let start = LocalState::new()
+        .with_datum("door_open", false)
+        .with_datum("price", 7_i64)
+        .with_datum("quoted", 0_i64);
+    let goal = Goal::new()
+        .with_req("door_open", Compare::equals(true))
+        .with_req("quoted", Compare::equals(7_i64));
+    for strategy in [PlanningStrategy::StartToGoal, PlanningStrategy::GoalToStart] {
+        let plan = make_plan_with_strategy(strategy, &start, &actions, &goal).unwrap();
+        assert_eq!(Cost::from(2), plan.cost, "{strategy:?}");
+        assert_eq!(
+            Some(&Datum::Bool(true)),
+            plan.final_state().get("door_open")
+        );
+        assert_eq!(Some(&Datum::I64(7)), plan.final_state().get("quoted"));
+    }
+    assert_admissible(&start, &actions, &goal);
+
+    // A removed key fails comparisons instead of being a mistake, until it is copied again
+    let goal = Goal::new().with_req("quoted", Compare::equals(6_i64));
+    assert_eq!(
+        Err(PlanError::SearchExhausted),
+        make_plan(&start, &actions[1..], &goal)
+    );
+    let start = LocalState::new().with_datum("price", 7_i64);
+    let goal = Goal::new().with_req("quoted", Compare::equals(7_i64));
+    let plan = make_plan(&start, &actions[1..], &goal).unwrap();
+    assert_eq!(vec!["quote"], plan.actions().collect::<Vec<_>>());
+
+    // Doubling over and over never reaches 3, and overflows instead of wrapping around
+    let double = Action::new("double").with_mutator(Mutator::multiply("gold", 2_i64));
+    let start = LocalState::new().with_datum("gold", 1_i64);
+    let goal = Goal::new().with_req("gold", Compare::equals(3_i64));
+    assert_eq!(
+        Err(PlanError::Overflow {
+            key: Some("gold".into())
+        }),
+        make_plan(&start, &[double], &goal)
+    );
+
+    let divide = Action::new("divide").with_mutator(Mutator::divide("gold", 0_i64));
+    let start = LocalState::new().with_datum("gold", 10_i64);
+    let goal = Goal::new().with_req("gold", Compare::equals(1_i64));
+    assert_eq!(
+        Err(PlanError::DivisionByZero {
+            key: Some("gold".into())
+        }),
+        make_plan(&start, &[divide], &goal)
+    );
 }
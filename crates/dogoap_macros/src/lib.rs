@@ -65,8 +65,8 @@ pub fn datum_component_derive(input: TokenStream) -> TokenStream {
     let genenerated = quote! {
 
         impl DatumComponent for #name {
-            fn field_key(&self) -> String {
-                #snake_case_name.to_owned()
+            fn field_key(&self) -> Key {
+                Self::key()
             }
 
             fn field_value(&self) -> Datum {
@@ -75,35 +75,90 @@ pub fn datum_component_derive(input: TokenStream) -> TokenStream {
         }
 
         impl #name {
-            pub fn key() -> String {
-                #snake_case_name.to_owned()
+            pub fn key() -> Key {
+                static KEY: std::sync::OnceLock<Key> = std::sync::OnceLock::new();
+                *KEY.get_or_init(|| Key::new(#snake_case_name))
             }
         }
 
         impl MutatorTrait<#field_type> for #name {
             fn set(val: #field_type) -> Mutator {
-                Mutator::Set(#snake_case_name.to_string(), #field_enum_variant(val))
+                Mutator::Set(Self::key(), #field_enum_variant(val))
             }
             fn increase(val: #field_type) -> Mutator {
-                Mutator::Increment(#snake_case_name.to_string(), #field_enum_variant(val))
+                Mutator::Increment(Self::key(), #field_enum_variant(val))
             }
             fn decrease(val: #field_type) -> Mutator {
-                Mutator::Decrement(#snake_case_name.to_string(), #field_enum_variant(val))
+                Mutator::Decrement(Self::key(), #field_enum_variant(val))
+            }
+            fn multiply(val: #field_type) -> Mutator {
+                Mutator::Multiply(Self::key(), #field_enum_variant(val))
+            }
+            fn divide(val: #field_type) -> Mutator {
+                Mutator::Divide(Self::key(), #field_enum_variant(val))
+            }
+            fn min(val: #field_type) -> Mutator {
+                Mutator::Min(Self::key(), #field_enum_variant(val))
+            }
+            fn max(val: #field_type) -> Mutator {
+                Mutator::Max(Self::key(), #field_enum_variant(val))
+            }
+            fn copy_from(key: impl Into<Key>) -> Mutator {
+                Mutator::copy_from(Self::key(), key)
+            }
+            fn toggle() -> Mutator {
+                Mutator::Toggle(Self::key())
+            }
+            fn add_key(key: impl Into<Key>) -> Mutator {
+                Mutator::add_key(Self::key(), key)
+            }
+            fn remove() -> Mutator {
+                Mutator::Remove(Self::key())
             }
         }
 
         impl Precondition<#field_type> for #name {
-            fn is(val: #field_type) -> (String, Compare) {
-                (#snake_case_name.to_string(), Compare::Equals(#field_enum_variant(val)))
+            fn is(val: #field_type) -> (Key, Compare) {
+                (Self::key(), Compare::Equals(#field_enum_variant(val)))
+            }
+            fn is_not(val: #field_type) -> (Key, Compare) {
+                (Self::key(), Compare::NotEquals(#field_enum_variant(val)))
+            }
+            fn is_more_than(val: #field_type) -> (Key, Compare) {
+                (Self::key(), Compare::GreaterThan(#field_enum_variant(val)))
+            }
+            fn is_less_than(val: #field_type) -> (Key, Compare) {
+                (Self::key(), Compare::LessThan(#field_enum_variant(val)))
+            }
+            fn is_at_least(val: #field_type) -> (Key, Compare) {
+                (Self::key(), Compare::GreaterThanEquals(#field_enum_variant(val)))
+            }
+            fn is_at_most(val: #field_type) -> (Key, Compare) {
+                (Self::key(), Compare::LessThanEquals(#field_enum_variant(val)))
+            }
+            fn is_between(min: #field_type, max: #field_type) -> (Key, Compare) {
+                (Self::key(), Compare::InRange(#field_enum_variant(min), #field_enum_variant(max)))
+            }
+            fn is_one_of(vals: impl IntoIterator<Item = #field_type>) -> (Key, Compare) {
+                (Self::key(), Compare::OneOf(vals.into_iter().map(|val| #field_enum_variant(val)).collect()))
+            }
+            fn is_key(key: impl Into<Key>) -> (Key, Compare) {
+                (Self::key(), Compare::equals_key(key))
+            }
+            fn is_not_key(key: impl Into<Key>) -> (Key, Compare) {
+                (Self::key(), Compare::not_equals_key(key))
+            }
+            fn is_more_than_key(key: impl Into<Key>) -> (Key, Compare) {
+                (Self::key(), Compare::greater_than_key(key))
             }
-            fn is_not(val: #field_type) -> (String, Compare) {
-                (#snake_case_name.to_string(), Compare::NotEquals(#field_enum_variant(val)))
+            fn is_less_than_key(key: impl Into<Key>) -> (Key, Compare) {
+                (Self::key(), Compare::less_than_key(key))
             }
-            fn is_more(val: #field_type) -> (String, Compare) {
-                (#snake_case_name.to_string(), Compare::GreaterThanEquals(#field_enum_variant(val)))
+            fn is_at_least_key(key: impl Into<Key>) -> (Key, Compare) {
+                (Self::key(), Compare::greater_than_equals_key(key))
             }
-            fn is_less(val: #field_type) -> (String, Compare) {
-                (#snake_case_name.to_string(), Compare::LessThanEquals(#field_enum_variant(val)))
+            fn is_at_most_key(key: impl Into<Key>) -> (Key, Compare) {
+                (Self::key(), Compare::less_than_equals_key(key))
             }
         }
     };
@@ -132,13 +187,13 @@ pub fn datum_component_derive(input: TokenStream) -> TokenStream {
 /// // Used as a Mutator:
 /// assert_eq!(
 ///     AtLocation::set(Location::Home),
-///     Mutator::Increment("at_location".to_string(), Datum::Enum(Location::Home as usize))
+///     Mutator::Increment("at_location".into(), Datum::Enum(Location::Home as usize))
 /// );
 ///
 /// // Used as a Precondition:
 /// assert_eq!(
 ///     AtLocation::is(Location::Outside),
-///     ("at_location".to_string(), Compare::Equals(Datum::Enum(Location::Outside as usize)))
+///     ("at_location".into(), Compare::Equals(Datum::Enum(Location::Outside as usize)))
 /// )
 /// ```
 #[proc_macro_derive(EnumComponent)]
@@ -167,8 +222,8 @@ pub fn enum_component_derive(input: TokenStream) -> TokenStream {
     let genenerated = quote! {
 
         impl DatumComponent for #name {
-            fn field_key(&self) -> String {
-                #snake_case_name.to_owned()
+            fn field_key(&self) -> Key {
+                Self::key()
             }
 
             fn field_value(&self) -> Datum {
@@ -177,14 +232,15 @@ pub fn enum_component_derive(input: TokenStream) -> TokenStream {
         }
 
         impl #name {
-            pub fn key() -> String {
-                #snake_case_name.to_owned()
+            pub fn key() -> Key {
+                static KEY: std::sync::OnceLock<Key> = std::sync::OnceLock::new();
+                *KEY.get_or_init(|| Key::new(#snake_case_name))
             }
         }
 
         impl MutatorTrait<#field_type> for #name {
             fn set(val: #field_type) -> Mutator {
-                Mutator::Set(#snake_case_name.to_string(), #field_enum_variant(val as usize))
+                Mutator::Set(Self::key(), #field_enum_variant(val as usize))
             }
             fn increase(val: #field_type) -> Mutator {
                 panic!("You cannot call .increase on a Enum!")
@@ -192,20 +248,74 @@ pub fn enum_component_derive(input: TokenStream) -> TokenStream {
             fn decrease(val: #field_type) -> Mutator {
                 panic!("You cannot call .increase on a Enum!")
             }
+            fn multiply(val: #field_type) -> Mutator {
+                panic!("You cannot call .multiply on a Enum!")
+            }
+            fn divide(val: #field_type) -> Mutator {
+                panic!("You cannot call .divide on a Enum!")
+            }
+            fn min(val: #field_type) -> Mutator {
+                panic!("You cannot call .min on a Enum!")
+            }
+            fn max(val: #field_type) -> Mutator {
+                panic!("You cannot call .max on a Enum!")
+            }
+            fn copy_from(key: impl Into<Key>) -> Mutator {
+                Mutator::copy_from(Self::key(), key)
+            }
+            fn toggle() -> Mutator {
+                panic!("You cannot call .toggle on a Enum!")
+            }
+            fn add_key(key: impl Into<Key>) -> Mutator {
+                panic!("You cannot call .add_key on a Enum!")
+            }
+            fn remove() -> Mutator {
+                Mutator::Remove(Self::key())
+            }
         }
 
         impl Precondition<#field_type> for #name {
-            fn is(val: #field_type) -> (String, Compare) {
-                (#snake_case_name.to_string(), Compare::Equals(#field_enum_variant(val as usize)))
+            fn is(val: #field_type) -> (Key, Compare) {
+                (Self::key(), Compare::Equals(#field_enum_variant(val as usize)))
+            }
+            fn is_not(val: #field_type) -> (Key, Compare) {
+                (Self::key(), Compare::NotEquals(#field_enum_variant(val as usize)))
+            }
+            fn is_more_than(val: #field_type) -> (Key, Compare) {
+                panic!("You cannot call .is_more_than on a Enum!")
+            }
+            fn is_less_than(val: #field_type) -> (Key, Compare) {
+                panic!("You cannot call .is_less_than on a Enum!")
+            }
+            fn is_at_least(val: #field_type) -> (Key, Compare) {
+                panic!("You cannot call .is_at_least on a Enum!")
+            }
+            fn is_at_most(val: #field_type) -> (Key, Compare) {
+                panic!("You cannot call .is_at_most on a Enum!")
+            }
+            fn is_between(min: #field_type, max: #field_type) -> (Key, Compare) {
+                panic!("You cannot call .is_between on a Enum!")
+            }
+            fn is_one_of(vals: impl IntoIterator<Item = #field_type>) -> (Key, Compare) {
+                (Self::key(), Compare::OneOf(vals.into_iter().map(|val| #field_enum_variant(val as usize)).collect()))
+            }
+            fn is_key(key: impl Into<Key>) -> (Key, Compare) {
+                (Self::key(), Compare::equals_key(key))
+            }
+            fn is_not_key(key: impl Into<Key>) -> (Key, Compare) {
+                (Self::key(), Compare::not_equals_key(key))
+            }
+            fn is_more_than_key(key: impl Into<Key>) -> (Key, Compare) {
+                panic!("You cannot call .is_more_than_key on a Enum!")
             }
-            fn is_not(val: #field_type) -> (String, Compare) {
-                (#snake_case_name.to_string(), Compare::NotEquals(#field_enum_variant(val as usize)))
+            fn is_less_than_key(key: impl Into<Key>) -> (Key, Compare) {
+                panic!("You cannot call .is_less_than_key on a Enum!")
             }
-            fn is_more(val: #field_type) -> (String, Compare) {
-                panic!("You cannot call .is_more on a Enum!")
+            fn is_at_least_key(key: impl Into<Key>) -> (Key, Compare) {
+                panic!("You cannot call .is_at_least_key on a Enum!")
             }
-            fn is_less(val: #field_type) -> (String, Compare) {
-                panic!("You cannot call .is_less on a Enum!")
+            fn is_at_most_key(key: impl Into<Key>) -> (Key, Compare) {
+                panic!("You cannot call .is_at_most_key on a Enum!")
             }
         }
     };